@@ -0,0 +1,462 @@
+//! Check a sending domain's verification state and the DNS records it needs. These live at
+//! `/domains/{domain}` rather than under the domain's own message paths.
+//!
+//! Also manages the authorized recipients of sandbox domains, which live under Mailgun's
+//! `/v5` API
+
+#[cfg(feature = "blocking")]
+use crate::{api_url, blocking_client, check_status, versioned_base, Credentials, MailgunResult};
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct Domain {
+    pub name: String,
+    /// `active` once verified, otherwise `unverified` or `disabled`
+    pub state: String,
+    pub created_at: String,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub spam_action: Option<String>,
+    #[serde(default)]
+    pub wildcard: bool,
+}
+
+impl Domain {
+    pub fn is_verified(&self) -> bool {
+        self.state == "active"
+    }
+}
+
+/// A record to publish for the domain, and whether Mailgun found it
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct DnsRecord {
+    pub record_type: String,
+    /// Unset for receiving (`MX`) records, which go on the domain itself
+    pub name: Option<String>,
+    pub value: String,
+    /// `MX` records only
+    pub priority: Option<String>,
+    /// `valid`, `invalid` or `unknown`
+    pub valid: String,
+    /// What Mailgun last found in DNS for this record
+    #[serde(default)]
+    pub cached: Vec<String>,
+}
+
+impl DnsRecord {
+    pub fn is_valid(&self) -> bool {
+        self.valid == "valid"
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DomainResponse {
+    pub domain: Domain,
+    /// Only set by `verify_domain`
+    pub message: Option<String>,
+    pub sending_dns_records: Vec<DnsRecord>,
+    pub receiving_dns_records: Vec<DnsRecord>,
+}
+
+/// An address a sandbox domain may send to. Mailgun emails it a confirmation link when
+/// it's added, sends to it are rejected until that link is followed
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct AuthorizedRecipient {
+    pub email: String,
+    #[serde(default)]
+    pub activated: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AuthorizedRecipientsResponse {
+    pub recipients: Vec<AuthorizedRecipient>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AddAuthorizedRecipientResponse {
+    pub recipient: AuthorizedRecipient,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct DeleteAuthorizedRecipientResponse {
+    pub message: String,
+}
+
+#[cfg(feature = "blocking")]
+const DOMAINS_ENDPOINT: &str = "domains";
+#[cfg(feature = "blocking")]
+const AUTH_RECIPIENTS_ENDPOINT: &str = "sandbox/auth_recipients";
+
+#[cfg(feature = "blocking")]
+fn auth_recipients_url(creds: &Credentials) -> String {
+    format!(
+        "{}/{}",
+        versioned_base(creds, "v5"),
+        AUTH_RECIPIENTS_ENDPOINT
+    )
+}
+
+// curl -s --user 'api:YOUR_API_KEY' \
+//     https://api.mailgun.net/v3/domains/YOUR_DOMAIN_NAME
+/// Fetch the domain's state and DNS records
+/// [API docs](https://documentation.mailgun.com/en/latest/api-domains.html#domains)
+#[cfg(feature = "blocking")]
+pub fn get_domain(creds: &Credentials) -> MailgunResult<DomainResponse> {
+    let client = blocking_client(creds)?;
+    get_domain_with_client(&client, creds)
+}
+
+/// Same as `get_domain` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn get_domain_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+) -> MailgunResult<DomainResponse> {
+    let url = format!("{}/{}/{}", creds.api_base, DOMAINS_ENDPOINT, creds.domain);
+    let request_builder = client.get(url);
+    get_domain_with_request_builder(request_builder, creds)
+}
+
+/// Same as `get_domain` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn get_domain_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+) -> MailgunResult<DomainResponse> {
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: DomainResponse = res.json()?;
+    Ok(parsed)
+}
+
+// curl -s --user 'api:YOUR_API_KEY' -X PUT \
+//     https://api.mailgun.net/v3/domains/YOUR_DOMAIN_NAME/verify
+/// Ask Mailgun to check the domain's DNS records again, e.g. right after publishing them
+/// [API docs](https://documentation.mailgun.com/en/latest/api-domains.html#domains)
+#[cfg(feature = "blocking")]
+pub fn verify_domain(creds: &Credentials) -> MailgunResult<DomainResponse> {
+    let client = blocking_client(creds)?;
+    verify_domain_with_client(&client, creds)
+}
+
+/// Same as `verify_domain` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn verify_domain_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+) -> MailgunResult<DomainResponse> {
+    let url = format!(
+        "{}/{}/{}/verify",
+        creds.api_base, DOMAINS_ENDPOINT, creds.domain
+    );
+    let request_builder = client.put(url);
+    verify_domain_with_request_builder(request_builder, creds)
+}
+
+/// Same as `verify_domain` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn verify_domain_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+) -> MailgunResult<DomainResponse> {
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: DomainResponse = res.json()?;
+    Ok(parsed)
+}
+
+// curl -s --user 'api:YOUR_API_KEY' -G \
+//     https://api.mailgun.net/v5/sandbox/auth_recipients \
+//     -d sandbox='YOUR_SANDBOX_DOMAIN'
+/// List the addresses `sandbox_domain` may send to, and whether each has confirmed
+/// [API docs](https://documentation.mailgun.com/docs/mailgun/api-reference/openapi-final/tag/Domains/)
+#[cfg(feature = "blocking")]
+pub fn get_authorized_recipients(
+    creds: &Credentials,
+    sandbox_domain: &str,
+) -> MailgunResult<AuthorizedRecipientsResponse> {
+    let client = blocking_client(creds)?;
+    get_authorized_recipients_with_client(&client, creds, sandbox_domain)
+}
+
+/// Same as `get_authorized_recipients` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn get_authorized_recipients_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    sandbox_domain: &str,
+) -> MailgunResult<AuthorizedRecipientsResponse> {
+    let request_builder = client
+        .get(auth_recipients_url(creds))
+        .query(&[("sandbox", sandbox_domain)]);
+    get_authorized_recipients_with_request_builder(request_builder, creds)
+}
+
+/// Same as `get_authorized_recipients` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn get_authorized_recipients_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+) -> MailgunResult<AuthorizedRecipientsResponse> {
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: AuthorizedRecipientsResponse = res.json()?;
+    Ok(parsed)
+}
+
+// curl -s --user 'api:YOUR_API_KEY' -X POST \
+//     'https://api.mailgun.net/v5/sandbox/auth_recipients?sandbox=YOUR_SANDBOX_DOMAIN&email=bob@example.com'
+/// Authorize `email` as a recipient for `sandbox_domain`. Mailgun emails it a confirmation
+/// link, it isn't `activated` until that's followed
+/// [API docs](https://documentation.mailgun.com/docs/mailgun/api-reference/openapi-final/tag/Domains/)
+#[cfg(feature = "blocking")]
+pub fn add_authorized_recipient(
+    creds: &Credentials,
+    sandbox_domain: &str,
+    email: &str,
+) -> MailgunResult<AddAuthorizedRecipientResponse> {
+    let client = blocking_client(creds)?;
+    add_authorized_recipient_with_client(&client, creds, sandbox_domain, email)
+}
+
+/// Same as `add_authorized_recipient` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn add_authorized_recipient_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    sandbox_domain: &str,
+    email: &str,
+) -> MailgunResult<AddAuthorizedRecipientResponse> {
+    let request_builder = client
+        .post(auth_recipients_url(creds))
+        .query(&[("sandbox", sandbox_domain), ("email", email)]);
+    add_authorized_recipient_with_request_builder(request_builder, creds)
+}
+
+/// Same as `add_authorized_recipient` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn add_authorized_recipient_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+) -> MailgunResult<AddAuthorizedRecipientResponse> {
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: AddAuthorizedRecipientResponse = res.json()?;
+    Ok(parsed)
+}
+
+// curl -s --user 'api:YOUR_API_KEY' -X DELETE \
+//     'https://api.mailgun.net/v5/sandbox/auth_recipients/bob@example.com?sandbox=YOUR_SANDBOX_DOMAIN'
+/// Stop `sandbox_domain` from sending to `email`
+/// [API docs](https://documentation.mailgun.com/docs/mailgun/api-reference/openapi-final/tag/Domains/)
+#[cfg(feature = "blocking")]
+pub fn delete_authorized_recipient(
+    creds: &Credentials,
+    sandbox_domain: &str,
+    email: &str,
+) -> MailgunResult<DeleteAuthorizedRecipientResponse> {
+    let client = blocking_client(creds)?;
+    delete_authorized_recipient_with_client(&client, creds, sandbox_domain, email)
+}
+
+/// Same as `delete_authorized_recipient` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn delete_authorized_recipient_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    sandbox_domain: &str,
+    email: &str,
+) -> MailgunResult<DeleteAuthorizedRecipientResponse> {
+    let url = api_url(&auth_recipients_url(creds), &[email])?;
+    let request_builder = client.delete(url).query(&[("sandbox", sandbox_domain)]);
+    delete_authorized_recipient_with_request_builder(request_builder, creds)
+}
+
+/// Same as `delete_authorized_recipient` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn delete_authorized_recipient_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+) -> MailgunResult<DeleteAuthorizedRecipientResponse> {
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: DeleteAuthorizedRecipientResponse = res.json()?;
+    Ok(parsed)
+}
+
+#[cfg(all(test, feature = "blocking"))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const KEY: &str = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+
+    fn response(state: &str, spf: &str) -> serde_json::Value {
+        json!({
+            "domain": {
+                "created_at": "Wed, 10 Jul 2013 19:26:52 GMT",
+                "name": "domains.example.com",
+                "smtp_login": "postmaster@domains.example.com",
+                "spam_action": "disabled",
+                "state": state,
+                "type": "custom",
+                "wildcard": false
+            },
+            "receiving_dns_records": [{
+                "cached": ["mxa.mailgun.org"],
+                "priority": "10",
+                "record_type": "MX",
+                "valid": "valid",
+                "value": "mxa.mailgun.org"
+            }],
+            "sending_dns_records": [{
+                "cached": [],
+                "name": "domains.example.com",
+                "record_type": "TXT",
+                "valid": spf,
+                "value": "v=spf1 include:mailgun.org ~all"
+            }]
+        })
+    }
+
+    #[test]
+    fn get_domain_records() {
+        let creds = Credentials::with_base(mockito::server_url(), KEY, "domains.example.com");
+        let m = mockito::mock("GET", "/domains/domains.example.com")
+            .with_status(200)
+            .with_body(response("unverified", "unknown").to_string())
+            .create();
+        let res = get_domain(&creds);
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+        let parsed = res.unwrap();
+        assert!(!parsed.domain.is_verified());
+        assert_eq!(parsed.domain.kind, Some("custom".to_string()));
+        assert_eq!(parsed.message, None);
+        assert_eq!(
+            parsed.sending_dns_records,
+            vec![DnsRecord {
+                record_type: "TXT".to_string(),
+                name: Some("domains.example.com".to_string()),
+                value: "v=spf1 include:mailgun.org ~all".to_string(),
+                priority: None,
+                valid: "unknown".to_string(),
+                cached: vec![],
+            }]
+        );
+        assert!(!parsed.sending_dns_records[0].is_valid());
+        let mx = &parsed.receiving_dns_records[0];
+        assert_eq!(mx.name, None);
+        assert_eq!(mx.priority, Some("10".to_string()));
+        assert!(mx.is_valid());
+    }
+
+    #[test]
+    fn verify() {
+        let creds = Credentials::with_base(mockito::server_url(), KEY, "domains.example.com");
+        let mut body = response("active", "valid");
+        body["message"] = json!("Domain DNS records have been updated");
+        let m = mockito::mock("PUT", "/domains/domains.example.com/verify")
+            .with_status(200)
+            .with_body(body.to_string())
+            .create();
+        let res = verify_domain(&creds);
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+        let parsed = res.unwrap();
+        assert!(parsed.domain.is_verified());
+        assert!(parsed.sending_dns_records.iter().all(DnsRecord::is_valid));
+        assert_eq!(
+            parsed.message,
+            Some("Domain DNS records have been updated".to_string())
+        );
+    }
+
+    #[test]
+    fn authorized_recipients() {
+        let base = format!("{}/sandbox-test/v3", mockito::server_url());
+        let creds = Credentials::with_base(base, KEY, "mg.example.com");
+        let sandbox = "sandbox123.mailgun.org";
+
+        let list = mockito::mock("GET", "/sandbox-test/v5/sandbox/auth_recipients")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "sandbox".into(),
+                sandbox.into(),
+            ))
+            .with_status(200)
+            .with_body(
+                json!({
+                    "recipients": [
+                        {"email": "dev@example.com", "activated": true},
+                        {"email": "qa@example.com", "activated": false}
+                    ]
+                })
+                .to_string(),
+            )
+            .create();
+        let res = get_authorized_recipients(&creds, sandbox);
+        assert!(res.is_ok(), "{:?}", &res);
+        list.assert();
+        assert_eq!(
+            res.unwrap().recipients,
+            vec![
+                AuthorizedRecipient {
+                    email: "dev@example.com".to_string(),
+                    activated: true,
+                },
+                AuthorizedRecipient {
+                    email: "qa@example.com".to_string(),
+                    activated: false,
+                },
+            ]
+        );
+
+        let add = mockito::mock("POST", "/sandbox-test/v5/sandbox/auth_recipients")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("sandbox".into(), sandbox.into()),
+                mockito::Matcher::UrlEncoded("email".into(), "new+1@example.com".into()),
+            ]))
+            .with_status(200)
+            .with_body(json!({"recipient": {"email": "new+1@example.com"}}).to_string())
+            .create();
+        let res = add_authorized_recipient(&creds, sandbox, "new+1@example.com");
+        assert!(res.is_ok(), "{:?}", &res);
+        add.assert();
+        assert!(!res.unwrap().recipient.activated);
+
+        let delete = mockito::mock(
+            "DELETE",
+            "/sandbox-test/v5/sandbox/auth_recipients/qa%2Fx@example.com",
+        )
+        .match_query(mockito::Matcher::UrlEncoded(
+            "sandbox".into(),
+            sandbox.into(),
+        ))
+        .with_status(200)
+        .with_body(json!({"message": "Recipient removed"}).to_string())
+        .create();
+        let res = delete_authorized_recipient(&creds, sandbox, "qa/x@example.com");
+        assert!(res.is_ok(), "{:?}", &res);
+        delete.assert();
+        assert_eq!(res.unwrap().message, "Recipient removed");
+    }
+}
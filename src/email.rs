@@ -2,17 +2,166 @@
 
 use chrono::prelude::*;
 
-use reqwest;
-use std::collections::HashMap;
+#[cfg(any(feature = "blocking", feature = "async"))]
+use base64::engine::general_purpose::STANDARD as BASE64;
+#[cfg(any(feature = "blocking", feature = "async"))]
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
+#[cfg(any(feature = "blocking", feature = "async"))]
+use crate::Credentials;
 pub use crate::EmailAddress;
-use crate::{Credentials, MailgunResult};
+#[cfg(feature = "blocking")]
+use crate::{api_url, blocking_client, check_status};
+use crate::{MailgunError, MailgunResult};
 
-///! `Html` and `Text` emails use different API params
+/// `Html` and `Text` emails use different API params
+#[derive(Clone)]
 pub enum MessageBody {
     Html(String),
     Text(String),
     HtmlAndText(String, String),
+    /// AMP for Email, sent as `amp-html`. Clients without AMP support render `html` (or
+    /// `text`) instead, so the HTML fallback is required
+    Amp {
+        amp: String,
+        html: String,
+        text: Option<String>,
+    },
+}
+
+impl MessageBody {
+    /// Send `html` along with a plain text fallback generated by stripping its tags
+    pub fn html_with_auto_text<T: ToString>(html: T) -> Self {
+        let html = html.to_string();
+        let text = html_to_text(&html);
+        MessageBody::HtmlAndText(html, text)
+    }
+
+    /// The form params carrying the content
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn into_params(self) -> Vec<(&'static str, String)> {
+        match self {
+            MessageBody::Text(text) => vec![("text", text)],
+            MessageBody::Html(html) => vec![("html", html)],
+            MessageBody::HtmlAndText(html, text) => vec![("text", text), ("html", html)],
+            MessageBody::Amp { amp, html, text } => {
+                let mut params = vec![("amp-html", amp), ("html", html)];
+                if let Some(text) = text {
+                    params.push(("text", text));
+                }
+                params
+            }
+        }
+    }
+}
+
+/// Elements whose content isn't readable text, dropped whole before stripping tags
+const NON_TEXT_ELEMENTS: [&str; 4] = ["head", "title", "style", "script"];
+
+fn html_to_text(html: &str) -> String {
+    let html = NON_TEXT_ELEMENTS
+        .iter()
+        .fold(html.to_string(), |html, name| drop_element(&html, name));
+    let mut stripped = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                stripped.push(' ');
+            }
+            _ if !in_tag => stripped.push(c),
+            _ => {}
+        }
+    }
+    decode_entities(&stripped)
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Remove every `<name ...>...</name>` (case-insensitive), an unclosed element runs to the end
+fn drop_element(html: &str, name: &str) -> String {
+    // ASCII lowercasing keeps byte offsets, so positions in `lower` index into `html`
+    let lower = html.to_ascii_lowercase();
+    let open = format!("<{}", name);
+    let close = format!("</{}", name);
+    let mut kept = String::with_capacity(html.len());
+    let mut pos = 0;
+    while let Some(found) = lower[pos..].find(&open) {
+        let start = pos + found;
+        let after_name = start + open.len();
+        let is_element = lower[after_name..]
+            .chars()
+            .next()
+            .is_none_or(|c| c == '>' || c == '/' || c.is_ascii_whitespace());
+        if !is_element {
+            // e.g. `<header>` when dropping `<head>`
+            kept.push_str(&html[pos..after_name]);
+            pos = after_name;
+            continue;
+        }
+        kept.push_str(&html[pos..start]);
+        pos = match lower[after_name..].find(&close) {
+            Some(end) => {
+                let end = after_name + end;
+                lower[end..].find('>').map_or(html.len(), |gt| end + gt + 1)
+            }
+            None => html.len(),
+        };
+        // keep the words on either side apart
+        kept.push(' ');
+    }
+    kept.push_str(&html[pos..]);
+    kept
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| decode_entity(&rest[1..end]).map(|c| (c, end)));
+        match entity {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let code = entity.strip_prefix('#')?;
+            let code = match code.strip_prefix('x').or_else(|| code.strip_prefix('X')) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
 }
 
 impl Default for MessageBody {
@@ -21,76 +170,455 @@ impl Default for MessageBody {
     }
 }
 
-///! An email to send through Mailgun. Won't send without a body
-#[derive(Default)]
+/// A `Message-Id` for `Message::message_id`, always wrapped in angle brackets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageId(String);
+
+impl MessageId {
+    /// Angle brackets are added if missing, ids without an `@` are rejected
+    pub fn new(id: &str) -> MailgunResult<MessageId> {
+        let id = id.trim();
+        let bare = id
+            .strip_prefix('<')
+            .and_then(|id| id.strip_suffix('>'))
+            .unwrap_or(id);
+        let valid = match bare.split_once('@') {
+            Some((local, domain)) => {
+                !local.is_empty()
+                    && !domain.is_empty()
+                    && !bare.contains(|c: char| c.is_whitespace() || c == '<' || c == '>')
+            }
+            None => false,
+        };
+        if !valid {
+            return Err(MailgunError::InvalidArgument(format!(
+                "'{}' is not a valid Message-Id",
+                id
+            )));
+        }
+        Ok(MessageId(format!("<{}>", bare)))
+    }
+}
+
+impl fmt::Display for MessageId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// An email to send through Mailgun. Won't send without a body
+#[derive(Clone)]
 pub struct Message {
     pub to: Vec<EmailAddress>,
     pub cc: Vec<EmailAddress>,
     pub bcc: Vec<EmailAddress>,
     pub subject: String,
     pub body: MessageBody,
+    /// Name of a stored template to render. The `body` is still sent as a fallback
     pub template: Option<String>,
+    /// Variables for the template, sent as JSON in the `X-Mailgun-Variables` header
+    pub template_variables: HashMap<String, serde_json::Value>,
     pub options: Vec<SendOptions>,
     pub attachments: Vec<Attachment>,
+    /// Files embedded in the body instead of offered as downloads. Reference them from an
+    /// `Html` (or `HtmlAndText`) body as `<img src="cid:logo.png">`, where `logo.png` is the
+    /// attachment's `name`
     pub inline: Vec<Attachment>,
+    /// Tag the message with its subject when no `SendOptions::Tag` is set
+    pub auto_tag: bool,
+    /// RFC 2047 encode non-ASCII subjects. On by default, turn it off with
+    /// `Message::encode_subject(false)`
+    pub encode_subject: bool,
+}
+
+impl Default for Message {
+    fn default() -> Message {
+        Message {
+            to: Vec::new(),
+            cc: Vec::new(),
+            bcc: Vec::new(),
+            subject: String::new(),
+            body: MessageBody::default(),
+            template: None,
+            template_variables: HashMap::new(),
+            options: Vec::new(),
+            attachments: Vec::new(),
+            inline: Vec::new(),
+            auto_tag: false,
+            encode_subject: true,
+        }
+    }
 }
 
+/// Fluent alternative to struct-update syntax, start one with `Message::builder()`
 #[derive(Default)]
+pub struct MessageBuilder {
+    message: Message,
+    html: Option<String>,
+    text: Option<String>,
+}
+
+impl MessageBuilder {
+    pub fn to(mut self, address: EmailAddress) -> Self {
+        self.message.to.push(address);
+        self
+    }
+
+    pub fn cc(mut self, address: EmailAddress) -> Self {
+        self.message.cc.push(address);
+        self
+    }
+
+    pub fn bcc(mut self, address: EmailAddress) -> Self {
+        self.message.bcc.push(address);
+        self
+    }
+
+    pub fn subject<T: ToString>(mut self, subject: T) -> Self {
+        self.message.subject = subject.to_string();
+        self
+    }
+
+    /// Combined with `text` this sends `MessageBody::HtmlAndText`
+    pub fn html<T: ToString>(mut self, html: T) -> Self {
+        self.html = Some(html.to_string());
+        self
+    }
+
+    pub fn text<T: ToString>(mut self, text: T) -> Self {
+        self.text = Some(text.to_string());
+        self
+    }
+
+    pub fn tag<T: ToString>(self, tag: T) -> Self {
+        self.option(SendOptions::Tag(tag.to_string()))
+    }
+
+    pub fn test_mode(self) -> Self {
+        self.option(SendOptions::TestMode)
+    }
+
+    pub fn option(mut self, option: SendOptions) -> Self {
+        self.message.options.push(option);
+        self
+    }
+
+    pub fn attachment(mut self, attachment: Attachment) -> Self {
+        self.message.attachments.push(attachment);
+        self
+    }
+
+    pub fn build(self) -> Message {
+        let mut message = self.message;
+        message.body = match (self.html, self.text) {
+            (Some(html), Some(text)) => MessageBody::HtmlAndText(html, text),
+            (Some(html), None) => MessageBody::Html(html),
+            (None, Some(text)) => MessageBody::Text(text),
+            (None, None) => message.body,
+        };
+        message
+    }
+}
+
+/// A file sent along with a `Message`, uploaded as a multipart `attachment` part
+#[derive(Default, Clone)]
 pub struct Attachment {
     pub name: String,
     pub content: Vec<u8>,
     pub mime_type: String,
 }
 
+impl Attachment {
+    /// Attach a complete email (e.g. when forwarding the original message) as a `.eml` file
+    pub fn eml<T: ToString>(name: T, raw_mime: String) -> Self {
+        let mut name = name.to_string();
+        if !name.ends_with(".eml") {
+            name.push_str(".eml");
+        }
+        Attachment {
+            name,
+            content: raw_mime.into_bytes(),
+            mime_type: String::from("message/rfc822"),
+        }
+    }
+}
+
+/// Mailgun rejects tags longer than this many bytes
+#[cfg(any(feature = "blocking", feature = "async"))]
+const MAX_TAG_LEN: usize = 128;
+
+/// Longest UTF-8 chunk whose base64 still fits a 75 character RFC 2047 encoded-word
+#[cfg(any(feature = "blocking", feature = "async"))]
+const MAX_ENCODED_WORD_BYTES: usize = 45;
+
+/// RFC 2047 `=?UTF-8?B?...?=` encoded-words for non-ASCII text, split on character
+/// boundaries to keep each word within the RFC's length limit. ASCII text is left alone
+#[cfg(any(feature = "blocking", feature = "async"))]
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() {
+        return value.to_string();
+    }
+    let mut words = Vec::new();
+    let mut chunk = String::new();
+    for c in value.chars() {
+        if chunk.len() + c.len_utf8() > MAX_ENCODED_WORD_BYTES {
+            words.push(format!("=?UTF-8?B?{}?=", BASE64.encode(chunk.as_bytes())));
+            chunk.clear();
+        }
+        chunk.push(c);
+    }
+    words.push(format!("=?UTF-8?B?{}?=", BASE64.encode(chunk.as_bytes())));
+    words.join(" ")
+}
+
 impl Message {
-    fn params(&self) -> HashMap<String, String> {
-        let mut params = HashMap::new();
+    pub fn builder() -> MessageBuilder {
+        MessageBuilder::default()
+    }
+
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn delivery_time(&self) -> Option<DateTime<Utc>> {
+        self.options.iter().find_map(|opt| match opt {
+            SendOptions::DeliveryTime(instant) => Some(*instant),
+            _ => None,
+        })
+    }
+
+    /// Derive an `o:tag` from the subject (ASCII only, truncated to 128 bytes)
+    /// when the message has no explicit tag
+    pub fn auto_tag_from_subject(mut self, enabled: bool) -> Self {
+        self.auto_tag = enabled;
+        self
+    }
+
+    /// Non-ASCII subjects are RFC 2047 encoded so every client decodes them the same way.
+    /// Pass `false` to send the subject untouched
+    pub fn encode_subject(mut self, enabled: bool) -> Self {
+        self.encode_subject = enabled;
+        self
+    }
+
+    /// Set our own `Message-Id` header, e.g. to match Mailgun events back to local records
+    pub fn message_id(mut self, id: MessageId) -> Self {
+        self.options
+            .push(SendOptions::Header(String::from("Message-Id"), id.0));
+        self
+    }
+
+    /// Drop attachments (and inline files) whose content is byte-for-byte identical to an
+    /// earlier one, compared by SHA-256, so each file is only uploaded once
+    pub fn dedupe_attachments(mut self) -> Self {
+        fn dedupe(attachments: &mut Vec<Attachment>) {
+            let mut seen = HashSet::new();
+            attachments.retain(|attachment| seen.insert(Sha256::digest(&attachment.content)));
+        }
+        dedupe(&mut self.attachments);
+        dedupe(&mut self.inline);
+        self
+    }
+
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn subject_tag(&self) -> Option<String> {
+        let tag = self
+            .subject
+            .split_whitespace()
+            .map(|word| {
+                word.chars()
+                    .filter(|c| c.is_ascii_graphic())
+                    .collect::<String>()
+            })
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<String>>()
+            .join(" ");
+        let tag = tag[..tag.len().min(MAX_TAG_LEN)].trim_end().to_string();
+        if tag.is_empty() {
+            None
+        } else {
+            Some(tag)
+        }
+    }
+
+    /// Repeatable options like `Tag` show up once per value, so this is a list of pairs
+    /// rather than a map
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn params(&self) -> Vec<(String, String)> {
+        let mut params = Vec::new();
 
         Message::add_recipients("to", &self.to, &mut params);
         Message::add_recipients("cc", &self.cc, &mut params);
         Message::add_recipients("bcc", &self.bcc, &mut params);
 
-        params.insert(String::from("subject"), self.subject.to_string());
+        let subject = if self.encode_subject {
+            encode_header(&self.subject)
+        } else {
+            self.subject.to_string()
+        };
+        params.push((String::from("subject"), subject));
 
         if let Some(template) = self.template.clone() {
-            params.insert("template".to_string(), template);
+            params.push(("template".to_string(), template));
+            if !self.template_variables.is_empty() {
+                let variables = serde_json::to_string(&self.template_variables)
+                    .expect("string keyed maps always serialize");
+                params.push(("h:X-Mailgun-Variables".to_string(), variables));
+            }
         }
 
         for opt in &self.options {
             opt.add_to(&mut params);
         }
 
+        if self.auto_tag && !params.iter().any(|(key, _)| key == "o:tag") {
+            if let Some(tag) = self.subject_tag() {
+                params.push((String::from("o:tag"), tag));
+            }
+        }
+
         params
     }
 
-    fn add_recipients(
-        field: &str,
-        addresses: &[EmailAddress],
-        params: &mut HashMap<String, String>,
-    ) {
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn add_recipients(field: &str, addresses: &[EmailAddress], params: &mut Vec<(String, String)>) {
         if !addresses.is_empty() {
             let joined = addresses
                 .iter()
                 .map(EmailAddress::to_string)
                 .collect::<Vec<String>>()
                 .join(",");
-            params.insert(field.to_owned(), joined);
+            params.push((field.to_owned(), joined));
         }
     }
 }
 
-///! Some of the parameters exposed by the mailgun send API
+/// Some of the parameters exposed by the mailgun send API. `secondary_dkim`,
+/// `optimize_period` and `time_zone_localize` are the checked way to build their options,
+/// constructing those variants directly skips validation
+#[derive(Debug, PartialEq, Clone)]
 pub enum SendOptions {
-    TestMode,                    // o:testmode
-    DeliveryTime(DateTime<Utc>), // o:deliverytime
-    Header(String, String),      // h:X-My-Header
-    Tag(String),                 // o:tag
+    TestMode,                      // o:testmode
+    DeliveryTime(DateTime<Utc>),   // o:deliverytime
+    Header(String, String),        // h:X-My-Header
+    Tag(String),                   // o:tag
+    SecondaryDkim(String),         // o:secondary-dkim, `domain/selector`
+    Tracking(bool),                // o:tracking
+    TrackingClicks(ClickTracking), // o:tracking-clicks
+    TrackingOpens(bool),           // o:tracking-opens
+    ReplyTo(EmailAddress),         // h:Reply-To
+    Variable(String, String),      // v:my-var
+    RequireTls(bool),              // o:require-tls, bounce rather than deliver in plaintext
+    SkipVerification(bool),        // o:skip-verification, accept any TLS certificate
+    OptimizePeriod(String),        // o:deliverytime-optimize-period
+    TimeZoneLocalize(String),      // o:time-zone-localize
+}
+
+/// Values accepted by `o:tracking-clicks`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ClickTracking {
+    Yes,
+    No,
+    HtmlOnly,
+}
+
+impl ClickTracking {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ClickTracking::Yes => "yes",
+            ClickTracking::No => "no",
+            ClickTracking::HtmlOnly => "htmlonly",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<ClickTracking> {
+        match value {
+            "yes" => Some(ClickTracking::Yes),
+            "no" => Some(ClickTracking::No),
+            "htmlonly" => Some(ClickTracking::HtmlOnly),
+            _ => None,
+        }
+    }
+}
+
+fn yes_no(flag: bool) -> String {
+    String::from(if flag { "yes" } else { "no" })
+}
+
+fn from_yes_no(value: &str) -> Option<bool> {
+    match value {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
 }
 
 impl SendOptions {
-    fn add_to(&self, params: &mut HashMap<String, String>) {
+    /// Add a second DKIM signature with the key published at `selector` on `domain`, e.g.
+    /// to sign as a customer's domain as well as the sending one. The domain must be a
+    /// valid hostname and the selector a DNS name
+    pub fn secondary_dkim<T: ToString>(domain: T, selector: T) -> MailgunResult<SendOptions> {
+        let domain = domain.to_string();
+        let selector = selector.to_string();
+        if !is_hostname(&domain) {
+            return Err(MailgunError::InvalidArgument(format!(
+                "'{}' is not a valid DKIM domain",
+                domain
+            )));
+        }
+        if !is_selector(&selector) {
+            return Err(MailgunError::InvalidArgument(format!(
+                "'{}' is not a valid DKIM selector",
+                selector
+            )));
+        }
+        Ok(SendOptions::SecondaryDkim(format!(
+            "{}/{}",
+            domain, selector
+        )))
+    }
+
+    /// Send-time optimization: deliver when the recipient is most likely to engage, within
+    /// `period` (e.g. `24h` or `2d`) of the send. Mailgun accepts 24 to 72 hours. Needs a
+    /// Mailgun plan that includes send-time optimization
+    pub fn optimize_period<T: ToString>(period: T) -> MailgunResult<SendOptions> {
+        let period = period.to_string();
+        let hours = match (period.strip_suffix('h'), period.strip_suffix('d')) {
+            (Some(hours), _) => parse_digits(hours),
+            (_, Some(days)) => parse_digits(days).map(|days| days.saturating_mul(24)),
+            _ => None,
+        };
+        match hours {
+            Some(hours) if (24..=72).contains(&hours) => Ok(SendOptions::OptimizePeriod(period)),
+            Some(_) => Err(MailgunError::InvalidArgument(format!(
+                "optimize period '{}' is outside 24h to 72h",
+                period
+            ))),
+            None => Err(MailgunError::InvalidArgument(format!(
+                "'{}' is not a period like 24h or 2d",
+                period
+            ))),
+        }
+    }
+
+    /// Deliver at `time` (`HH:MM`, 24 hour clock) in each recipient's own time zone. Needs a
+    /// Mailgun plan that includes time zone optimization
+    pub fn time_zone_localize<T: ToString>(time: T) -> MailgunResult<SendOptions> {
+        let time = time.to_string();
+        let valid =
+            |part: &str, max: u32| part.len() == 2 && parse_digits(part).is_some_and(|n| n <= max);
+        match time.split_once(':') {
+            Some((hours, minutes)) if valid(hours, 23) && valid(minutes, 59) => {
+                Ok(SendOptions::TimeZoneLocalize(time))
+            }
+            _ => Err(MailgunError::InvalidArgument(format!(
+                "'{}' is not a 24 hour HH:MM time",
+                time
+            ))),
+        }
+    }
+
+    /// The API param name and value this option is sent as
+    pub fn as_param(&self) -> (String, String) {
         use self::SendOptions::*;
-        let (key, value) = match self {
+        match self {
             TestMode => (String::from("o:testmode"), String::from("yes")),
             DeliveryTime(instant) => (String::from("o:deliverytime"), instant.to_rfc2822()),
             Header(header, val) => {
@@ -98,17 +626,168 @@ impl SendOptions {
                 (key, val.to_owned())
             }
             Tag(tag) => (String::from("o:tag"), tag.to_owned()),
-        };
-        params.insert(key, value);
+            SecondaryDkim(domain_selector) => {
+                (String::from("o:secondary-dkim"), domain_selector.to_owned())
+            }
+            Tracking(enabled) => (String::from("o:tracking"), yes_no(*enabled)),
+            TrackingClicks(clicks) => (
+                String::from("o:tracking-clicks"),
+                clicks.as_str().to_owned(),
+            ),
+            TrackingOpens(enabled) => (String::from("o:tracking-opens"), yes_no(*enabled)),
+            ReplyTo(address) => (String::from("h:Reply-To"), address.to_string()),
+            Variable(name, val) => (format!("v:{}", name), val.to_owned()),
+            RequireTls(required) => (String::from("o:require-tls"), yes_no(*required)),
+            SkipVerification(skip) => (String::from("o:skip-verification"), yes_no(*skip)),
+            OptimizePeriod(period) => (
+                String::from("o:deliverytime-optimize-period"),
+                period.to_owned(),
+            ),
+            TimeZoneLocalize(time) => (String::from("o:time-zone-localize"), time.to_owned()),
+        }
+    }
+
+    /// Inverse of `as_param`, returns `None` for params that aren't a known option
+    pub fn from_param(key: &str, value: &str) -> Option<SendOptions> {
+        use self::SendOptions::*;
+        match key {
+            "o:testmode" if value == "yes" => Some(TestMode),
+            "o:deliverytime" => DateTime::parse_from_rfc2822(value)
+                .ok()
+                .map(|instant| DeliveryTime(instant.with_timezone(&Utc))),
+            "o:tag" => Some(Tag(value.to_owned())),
+            "o:secondary-dkim" => value
+                .split_once('/')
+                .and_then(|(domain, selector)| SendOptions::secondary_dkim(domain, selector).ok()),
+            "o:tracking" => from_yes_no(value).map(Tracking),
+            "o:tracking-clicks" => ClickTracking::from_str(value).map(TrackingClicks),
+            "o:tracking-opens" => from_yes_no(value).map(TrackingOpens),
+            // e.g. a list of addresses, which `ReplyTo` can't hold
+            "h:Reply-To" => Some(
+                value
+                    .parse()
+                    .map(ReplyTo)
+                    .unwrap_or_else(|_| Header(String::from("Reply-To"), value.to_owned())),
+            ),
+            "o:require-tls" => from_yes_no(value).map(RequireTls),
+            "o:skip-verification" => from_yes_no(value).map(SkipVerification),
+            "o:deliverytime-optimize-period" => SendOptions::optimize_period(value).ok(),
+            "o:time-zone-localize" => SendOptions::time_zone_localize(value).ok(),
+            _ => {
+                if let Some(header) = key.strip_prefix("h:") {
+                    Some(Header(header.to_owned(), value.to_owned()))
+                } else {
+                    key.strip_prefix("v:")
+                        .map(|name| Variable(name.to_owned(), value.to_owned()))
+                }
+            }
+        }
+    }
+
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn add_to(&self, params: &mut Vec<(String, String)>) {
+        params.push(self.as_param());
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+fn is_selector(selector: &str) -> bool {
+    !selector.is_empty()
+        && selector.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+fn is_hostname(name: &str) -> bool {
+    name.len() <= 253
+        && name.contains('.')
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Plain ASCII digits only, `u32::from_str` would also take a leading `+`
+fn parse_digits(digits: &str) -> Option<u32> {
+    if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        digits.parse().ok()
+    } else {
+        None
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Default)]
 pub struct SendResponse {
     pub message: String,
     pub id: String,
+    #[serde(skip)]
+    scheduled_for: Option<DateTime<Utc>>,
+}
+
+/// Whether Mailgun queued a message for immediate delivery or scheduled it for later
+#[derive(Debug, PartialEq)]
+pub enum SendStatus {
+    Queued,
+    Scheduled(DateTime<Utc>),
+}
+
+impl SendResponse {
+    /// The `SendOptions::DeliveryTime` the message was sent with, if any. Filled in by this
+    /// crate, Mailgun's response doesn't include it
+    pub fn scheduled_for(&self) -> Option<DateTime<Utc>> {
+        self.scheduled_for
+    }
+
+    pub fn status(&self) -> SendStatus {
+        match self.scheduled_for {
+            Some(instant) => SendStatus::Scheduled(instant),
+            None => SendStatus::Queued,
+        }
+    }
+}
+
+/// A message Mailgun kept in storage, e.g. an inbound route with `store()` or a sent
+/// message from its events' `storage.url`
+#[derive(Deserialize, Debug)]
+pub struct StoredMessage {
+    pub sender: String,
+    pub recipients: String,
+    pub from: Option<String>,
+    pub subject: Option<String>,
+    #[serde(rename = "body-plain")]
+    pub body_plain: Option<String>,
+    #[serde(rename = "body-html")]
+    pub body_html: Option<String>,
+    /// The plain text body without quoted replies and signature
+    #[serde(rename = "stripped-text")]
+    pub stripped_text: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<StoredAttachment>,
+}
+
+/// Where to download a stored message's attachment, the content isn't included inline
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct StoredAttachment {
+    pub url: String,
+    pub name: String,
+    #[serde(rename = "content-type")]
+    pub content_type: String,
+    pub size: u64,
+}
+
+/// Mailgun's confirmation that a scheduled message was cancelled
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct DeleteMessageResponse {
+    pub message: String,
 }
 
+#[cfg(any(feature = "blocking", feature = "async"))]
 const MESSAGES_ENDPOINT: &str = "messages";
 
 // curl -s --user 'api:YOUR_API_KEY' \
@@ -120,16 +799,19 @@ const MESSAGES_ENDPOINT: &str = "messages";
 //     -F text='Testing some Mailgun awesomeness!'
 /// Sends a single email from the specified sender address
 /// [API docs](https://documentation.mailgun.com/en/latest/api-sending.html#sending)
+#[cfg(feature = "blocking")]
 pub fn send_email(
     creds: &Credentials,
     sender: &EmailAddress,
     msg: Message,
 ) -> MailgunResult<SendResponse> {
-    let client = reqwest::blocking::Client::new();
+    let client = blocking_client(creds)?;
     send_with_client(&client, creds, sender, msg)
 }
 
-/// Same as `send_email` but with an externally managed client
+/// Same as `send_email` but with an externally managed client.
+/// Retries rate limited and failed sends if the credentials have a `RetryConfig`
+#[cfg(feature = "blocking")]
 pub fn send_with_client(
     client: &reqwest::blocking::Client,
     creds: &Credentials,
@@ -137,12 +819,42 @@ pub fn send_with_client(
     msg: Message,
 ) -> MailgunResult<SendResponse> {
     let url = format!("{}/{}/{}", creds.api_base, creds.domain, MESSAGES_ENDPOINT);
+    if let Some(retry) = creds.retry {
+        for attempt in 0..retry.max_retries {
+            let request_builder = client.post(&url);
+            match send_with_request_builder(request_builder, creds, sender, msg.clone()) {
+                Err(err) if err.is_retryable() => std::thread::sleep(retry.delay(attempt, &err)),
+                res => return res,
+            }
+        }
+    }
     let request_builder = client.post(url);
     send_with_request_builder(request_builder, creds, sender, msg)
 }
 
+/// Sends each message to its paired recipient as a separate request, reusing `client`'s
+/// connection. Batch recipient-variables can't carry binary data, so use this when every
+/// recipient needs their own attachments (invoices, personalized PDFs, ...).
+/// Any `to` already set on a message is replaced by its recipient
+#[cfg(feature = "blocking")]
+pub fn send_personalized(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    sender: &EmailAddress,
+    messages: Vec<(EmailAddress, Message)>,
+) -> Vec<MailgunResult<SendResponse>> {
+    messages
+        .into_iter()
+        .map(|(recipient, mut msg)| {
+            msg.to = vec![recipient];
+            send_with_client(client, creds, sender, msg)
+        })
+        .collect()
+}
+
 /// Same as `send_email` but with an externally managed request builder.
 /// Use this in case you want to send the mails to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
 pub fn send_with_request_builder(
     request_builder: reqwest::blocking::RequestBuilder,
     creds: &Credentials,
@@ -150,24 +862,21 @@ pub fn send_with_request_builder(
     msg: Message,
 ) -> MailgunResult<SendResponse> {
     let mut params = msg.params();
-    params.insert("from".to_string(), sender.to_string());
+    params.push(("from".to_string(), sender.to_string()));
+    let scheduled_for = msg.delivery_time();
+    let recipient = params
+        .iter()
+        .find(|(key, _)| key == "to")
+        .map(|(_, to)| to.clone())
+        .unwrap_or_default();
 
     let mut form = reqwest::blocking::multipart::Form::new();
     for (key, value) in params {
         form = form.text(key, value);
     }
     //add message content
-    match msg.body {
-        MessageBody::Text(text) => {
-            form = form.text("text", text);
-        }
-        MessageBody::Html(html) => {
-            form = form.text("html", html);
-        }
-        MessageBody::HtmlAndText(html, text) => {
-            form = form.text("text", text);
-            form = form.text("html", html);
-        }
+    for (key, value) in msg.body.into_params() {
+        form = form.text(key, value);
     }
     //add attachments
     for attachment in msg.attachments {
@@ -186,19 +895,113 @@ pub fn send_with_request_builder(
     let res = request_builder
         .basic_auth("api", Some(creds.api_key.clone()))
         .multipart(form)
-        .send()?
-        .error_for_status()?;
+        .send()?;
+    let res = check_status(res).map_err(|err| err.restricted_to(recipient).invalid_param())?;
+
+    let mut parsed: SendResponse = res.json()?;
+    parsed.scheduled_for = scheduled_for;
+    Ok(parsed)
+}
+
+// curl -s --user 'api:YOUR_API_KEY' \
+//     https://storage-us-east4.api.mailgun.net/v3/domains/YOUR_DOMAIN_NAME/messages/STORAGE_KEY
+/// Fetch a stored message. `url` is used as given, since Mailgun hands out storage URLs on
+/// hosts other than `api_base`
+/// [API docs](https://documentation.mailgun.com/en/latest/api-sending.html#retrieving-stored-messages)
+#[cfg(feature = "blocking")]
+pub fn get_stored_message(creds: &Credentials, url: &str) -> MailgunResult<StoredMessage> {
+    let client = blocking_client(creds)?;
+    get_stored_message_with_client(&client, creds, url)
+}
+
+/// Same as `get_stored_message` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn get_stored_message_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    url: &str,
+) -> MailgunResult<StoredMessage> {
+    get_stored_message_with_request_builder(client.get(url), creds)
+}
+
+/// Same as `get_stored_message` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn get_stored_message_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+) -> MailgunResult<StoredMessage> {
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: StoredMessage = res.json()?;
+    Ok(parsed)
+}
+
+// curl -s --user 'api:YOUR_API_KEY' -X DELETE \
+//     https://api.mailgun.net/v3/YOUR_DOMAIN_NAME/messages/STORAGE_KEY
+/// Cancel a message scheduled with `SendOptions::DeliveryTime` before it goes out.
+/// `storage_key` is the key from the message's storage URL, e.g. in its `accepted` event
+/// [API docs](https://documentation.mailgun.com/en/latest/api-sending.html#deleting-stored-messages)
+#[cfg(feature = "blocking")]
+pub fn delete_scheduled_message(
+    creds: &Credentials,
+    storage_key: &str,
+) -> MailgunResult<DeleteMessageResponse> {
+    let client = blocking_client(creds)?;
+    delete_scheduled_message_with_client(&client, creds, storage_key)
+}
+
+/// Same as `delete_scheduled_message` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn delete_scheduled_message_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    storage_key: &str,
+) -> MailgunResult<DeleteMessageResponse> {
+    let url = api_url(
+        &creds.api_base,
+        &[&creds.domain, MESSAGES_ENDPOINT, storage_key],
+    )?;
+    let request_builder = client.delete(url);
+    delete_scheduled_message_with_request_builder(request_builder, creds)
+}
+
+/// Same as `delete_scheduled_message` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn delete_scheduled_message_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+) -> MailgunResult<DeleteMessageResponse> {
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .send()?;
+    let res = check_status(res)?;
 
-    let parsed: SendResponse = res.json()?;
+    let parsed: DeleteMessageResponse = res.json()?;
     Ok(parsed)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "blocking")]
     use reqwest::StatusCode;
+    #[cfg(any(feature = "blocking", feature = "async"))]
     use serde_json::json;
 
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn param<'a>(params: &'a [(String, String)], key: &str) -> Option<&'a String> {
+        params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    #[cfg(any(feature = "blocking", feature = "async"))]
     #[test]
     fn message_recipients() {
         let msg = Message {
@@ -211,14 +1014,15 @@ mod tests {
         };
 
         let params = msg.params();
-        assert_eq!(params.get("to"), Some(&String::from("foo@bar.com")));
+        assert_eq!(param(&params, "to"), Some(&String::from("foo@bar.com")));
         assert_eq!(
-            params.get("cc"),
+            param(&params, "cc"),
             Some(&String::from("Tim <woo@woah.com>,z@c.c"))
         );
-        assert_eq!(params.get("bcc"), None);
+        assert_eq!(param(&params, "bcc"), None);
     }
 
+    #[cfg(any(feature = "blocking", feature = "async"))]
     #[test]
     fn send_options() {
         let msg = Message {
@@ -227,73 +1031,529 @@ mod tests {
                 SendOptions::DeliveryTime(Utc.timestamp_millis_opt(1431648000).unwrap()),
                 SendOptions::Header("X-For".to_owned(), "Fizz".to_owned()),
                 SendOptions::Tag("Important".to_owned()),
+                SendOptions::Tracking(true),
+                SendOptions::TrackingClicks(ClickTracking::HtmlOnly),
+                SendOptions::TrackingOpens(false),
+                SendOptions::RequireTls(true),
+                SendOptions::SkipVerification(false),
             ],
             ..Default::default()
         };
 
         let params = msg.params();
-        assert_eq!(params.get("o:testmode"), Some(&String::from("yes")));
+        assert_eq!(param(&params, "o:testmode"), Some(&String::from("yes")));
         assert_eq!(
-            params.get("o:deliverytime"),
+            param(&params, "o:deliverytime"),
             Some(&String::from("Sat, 17 Jan 1970 13:40:48 +0000"))
         );
-        assert_eq!(params.get("h:X-For"), Some(&String::from("Fizz")));
-        assert_eq!(params.get("o:tag"), Some(&String::from("Important")));
+        assert_eq!(param(&params, "h:X-For"), Some(&String::from("Fizz")));
+        assert_eq!(param(&params, "o:tag"), Some(&String::from("Important")));
+        assert_eq!(param(&params, "o:tracking"), Some(&String::from("yes")));
+        assert_eq!(
+            param(&params, "o:tracking-clicks"),
+            Some(&String::from("htmlonly"))
+        );
+        assert_eq!(
+            param(&params, "o:tracking-opens"),
+            Some(&String::from("no"))
+        );
+        assert_eq!(param(&params, "o:require-tls"), Some(&String::from("yes")));
+        assert_eq!(
+            param(&params, "o:skip-verification"),
+            Some(&String::from("no"))
+        );
     }
 
+    #[cfg(any(feature = "blocking", feature = "async"))]
     #[test]
-    fn request_unauthorized() {
-        // invalid key & domain
-        let creds = Credentials::new(
-            "key-your_key_here_that-is-very-long_and-still-goes-on-and-on-and-on",
-            "aksdfa32undkjns.com",
-        );
-        let recipient = EmailAddress::address("timmy@aksdfa32undkjns.com");
-        let message = Message {
-            to: vec![recipient],
-            subject: "Test email".to_string(),
+    fn multiple_tags_and_reply_to() {
+        let msg = Message {
+            options: vec![
+                SendOptions::Tag("newsletter".to_owned()),
+                SendOptions::Tag("march".to_owned()),
+                SendOptions::ReplyTo(EmailAddress::name_address("Support", "help@example.com")),
+            ],
             ..Default::default()
         };
-        let sender = EmailAddress::name_address("Nick Testla", "nick@aksdfa32undkjns.com");
 
-        let res = send_email(&creds, &sender, message);
-        assert!(res.is_err());
-        assert_eq!(res.unwrap_err().status(), Some(StatusCode::UNAUTHORIZED));
+        let params = msg.params();
+        let tags: Vec<&String> = params
+            .iter()
+            .filter(|(key, _)| key == "o:tag")
+            .map(|(_, tag)| tag)
+            .collect();
+        assert_eq!(tags, vec!["newsletter", "march"]);
+        assert_eq!(
+            param(&params, "h:Reply-To"),
+            Some(&String::from("Support <help@example.com>"))
+        );
     }
 
-    #[ignore]
+    #[cfg(any(feature = "blocking", feature = "async"))]
     #[test]
-    fn actually_send_email() {
-        // if you want to try actually sending an email w/ your credentials add them to this test
-        // and run it.
-        let domain = "sandbox-some_numbers_here_probably.mailgun.org";
-        let key = "something-secret-something-safe";
-        let recipient = "foo@bar.com";
-
-        let creds = Credentials::new(&key, &domain);
-        let recipient = EmailAddress::address(&recipient);
-        let message = Message {
-            to: vec![recipient],
-            subject: "Test email".to_string(),
-            body: MessageBody::Text(String::from(
-                "This email is from an mailgun_v3 automated test",
-            )),
+    fn custom_variables() {
+        let msg = Message {
+            options: vec![
+                SendOptions::Variable("order-id".to_owned(), "A-1234".to_owned()),
+                SendOptions::Variable("customer".to_owned(), "42".to_owned()),
+            ],
             ..Default::default()
         };
-        let sender = EmailAddress::name_address("Nick Testla", &format!("mailgun_v3@{}", &domain));
+
+        let params = msg.params();
+        assert_eq!(param(&params, "v:order-id"), Some(&String::from("A-1234")));
+        assert_eq!(param(&params, "v:customer"), Some(&String::from("42")));
+    }
+
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    #[test]
+    fn message_id() {
+        let msg =
+            Message::default().message_id(MessageId::new("order-1234@shop.example.com").unwrap());
+        assert_eq!(
+            param(&msg.params(), "h:Message-Id"),
+            Some(&String::from("<order-1234@shop.example.com>"))
+        );
+
+        let id = MessageId::new(" <abc@example.com> ").unwrap();
+        assert_eq!(id.to_string(), "<abc@example.com>");
+        let msg = Message::default().message_id(id);
+        assert_eq!(
+            param(&msg.params(), "h:Message-Id"),
+            Some(&String::from("<abc@example.com>"))
+        );
+
+        for bad in &[
+            "order-1234",
+            "@example.com",
+            "abc@",
+            "a b@example.com",
+            "<<a@b.c>>",
+        ] {
+            match MessageId::new(bad) {
+                Err(MailgunError::InvalidArgument(_)) => {}
+                _ => panic!("expected {} to be rejected", bad),
+            }
+        }
+    }
+
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    #[test]
+    fn secondary_dkim() {
+        let msg = Message {
+            options: vec![SendOptions::secondary_dkim("customer.com", "mg").unwrap()],
+            ..Default::default()
+        };
+
+        let params = msg.params();
+        assert_eq!(
+            param(&params, "o:secondary-dkim"),
+            Some(&String::from("customer.com/mg"))
+        );
+
+        for (domain, selector) in [
+            ("", "mg"),
+            ("localhost", "mg"),
+            ("bad_host.com", "mg"),
+            ("-bad.com", "mg"),
+            ("double..dot.com", "mg"),
+            ("customer.com", ""),
+            ("customer.com", "mg/2024"),
+            ("customer.com", "bad selector"),
+        ] {
+            match SendOptions::secondary_dkim(domain, selector) {
+                Err(MailgunError::InvalidArgument(_)) => {}
+                other => panic!(
+                    "expected {}/{} to be rejected, got {:?}",
+                    domain, selector, other
+                ),
+            }
+        }
+
+        assert_eq!(
+            SendOptions::from_param("o:secondary-dkim", "customer.com"),
+            None
+        );
+        assert_eq!(
+            SendOptions::from_param("o:secondary-dkim", "bad_host.com/mg"),
+            None
+        );
+    }
+
+    #[test]
+    fn send_options_round_trip() {
+        let options = vec![
+            SendOptions::TestMode,
+            SendOptions::DeliveryTime(Utc.timestamp_opt(1431648000, 0).unwrap()),
+            SendOptions::Header("X-For".to_owned(), "Fizz".to_owned()),
+            SendOptions::Tag("Important".to_owned()),
+            SendOptions::secondary_dkim("customer.com", "s1").unwrap(),
+            SendOptions::Tracking(false),
+            SendOptions::TrackingClicks(ClickTracking::Yes),
+            SendOptions::TrackingClicks(ClickTracking::No),
+            SendOptions::TrackingClicks(ClickTracking::HtmlOnly),
+            SendOptions::TrackingOpens(true),
+            SendOptions::ReplyTo(EmailAddress::address("help@example.com")),
+            SendOptions::Variable("order-id".to_owned(), "1234".to_owned()),
+            SendOptions::ReplyTo(EmailAddress::name_address("Help Desk", "help@example.com")),
+            SendOptions::RequireTls(true),
+            SendOptions::SkipVerification(true),
+            SendOptions::OptimizePeriod("24h".to_owned()),
+            SendOptions::TimeZoneLocalize("14:00".to_owned()),
+        ];
+        for opt in options {
+            let (key, value) = opt.as_param();
+            assert_eq!(SendOptions::from_param(&key, &value), Some(opt));
+        }
+
+        assert_eq!(SendOptions::from_param("o:testmode", "no"), None);
+        assert_eq!(SendOptions::from_param("o:tracking", "maybe"), None);
+        assert_eq!(SendOptions::from_param("o:deliverytime", "tomorrow"), None);
+        assert_eq!(SendOptions::from_param("subject", "Hello"), None);
+        assert_eq!(
+            SendOptions::from_param("h:Reply-To", "a@example.com, b@example.com"),
+            Some(SendOptions::Header(
+                "Reply-To".to_owned(),
+                "a@example.com, b@example.com".to_owned()
+            ))
+        );
+    }
+
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    #[test]
+    fn scheduling_options() {
+        let msg = Message {
+            options: vec![
+                SendOptions::optimize_period("24h").unwrap(),
+                SendOptions::time_zone_localize("09:30").unwrap(),
+            ],
+            ..Default::default()
+        };
+        let params = msg.params();
+        assert_eq!(
+            param(&params, "o:deliverytime-optimize-period"),
+            Some(&String::from("24h"))
+        );
+        assert_eq!(
+            param(&params, "o:time-zone-localize"),
+            Some(&String::from("09:30"))
+        );
+
+        assert!(SendOptions::optimize_period("3d").is_ok());
+        assert!(SendOptions::optimize_period("72h").is_ok());
+        for bad in &["24", "h", "24 h", "12h", "4d", "73h", "99999999999d"] {
+            assert!(
+                matches!(
+                    SendOptions::optimize_period(bad),
+                    Err(MailgunError::InvalidArgument(_))
+                ),
+                "expected {} to be rejected",
+                bad
+            );
+        }
+        for bad in &["9:30", "24:00", "14:60", "2pm", "+1:30"] {
+            assert!(
+                matches!(
+                    SendOptions::time_zone_localize(bad),
+                    Err(MailgunError::InvalidArgument(_))
+                ),
+                "expected {} to be rejected",
+                bad
+            );
+        }
+    }
+
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    #[test]
+    fn amp_body() {
+        let body = MessageBody::Amp {
+            amp: String::from("<html amp4email><body>hi</body></html>"),
+            html: String::from("<p>hi</p>"),
+            text: None,
+        };
+        let params = body.into_params();
+        assert_eq!(
+            params,
+            vec![
+                (
+                    "amp-html",
+                    String::from("<html amp4email><body>hi</body></html>")
+                ),
+                ("html", String::from("<p>hi</p>")),
+            ]
+        );
+
+        let body = MessageBody::Amp {
+            amp: String::from("<html amp4email></html>"),
+            html: String::from("<p>hi</p>"),
+            text: Some(String::from("hi")),
+        };
+        let keys: Vec<&str> = body.into_params().into_iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec!["amp-html", "html", "text"]);
+    }
+
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    #[test]
+    fn message_builder() {
+        let msg = Message::builder()
+            .to(EmailAddress::address("a@example.com"))
+            .to(EmailAddress::address("b@example.com"))
+            .cc(EmailAddress::name_address("Cee", "c@example.com"))
+            .subject("Spring sale")
+            .text("Everything is 20% off")
+            .tag("sale")
+            .tag("spring")
+            .test_mode()
+            .build();
+        let params = msg.params();
+        assert_eq!(param(&params, "to").unwrap(), "a@example.com,b@example.com");
+        assert_eq!(param(&params, "cc").unwrap(), "Cee <c@example.com>");
+        assert_eq!(param(&params, "subject").unwrap(), "Spring sale");
+        assert_eq!(param(&params, "o:testmode").unwrap(), "yes");
+        let tags: Vec<&String> = params
+            .iter()
+            .filter(|(key, _)| key == "o:tag")
+            .map(|(_, tag)| tag)
+            .collect();
+        assert_eq!(tags, vec!["sale", "spring"]);
+        match msg.body {
+            MessageBody::Text(text) => assert_eq!(text, "Everything is 20% off"),
+            _ => panic!("expected a text body"),
+        }
+
+        let msg = Message::builder().html("<b>hi</b>").text("hi").build();
+        match msg.body {
+            MessageBody::HtmlAndText(html, text) => {
+                assert_eq!(html, "<b>hi</b>");
+                assert_eq!(text, "hi");
+            }
+            _ => panic!("expected an html and text body"),
+        }
+    }
+
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    #[test]
+    fn encoded_subject() {
+        let msg = Message {
+            subject: String::from("Hello"),
+            ..Default::default()
+        };
+        assert_eq!(param(&msg.params(), "subject").unwrap(), "Hello");
+
+        let msg = Message {
+            subject: String::from("Café 🎉"),
+            ..Default::default()
+        };
+        assert_eq!(
+            param(&msg.params(), "subject").unwrap(),
+            "=?UTF-8?B?Q2Fmw6kg8J+OiQ==?="
+        );
+
+        let msg = Message {
+            subject: "é".repeat(40),
+            ..Default::default()
+        };
+        let params = msg.params();
+        let words: Vec<&str> = param(&params, "subject").unwrap().split(' ').collect();
+        assert_eq!(words.len(), 2);
+        assert!(words
+            .iter()
+            .all(|word| word.starts_with("=?UTF-8?B?") && word.len() <= 75));
+
+        let msg = Message {
+            subject: String::from("Café 🎉"),
+            ..Default::default()
+        }
+        .encode_subject(false);
+        assert_eq!(param(&msg.params(), "subject").unwrap(), "Café 🎉");
+    }
+
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    #[test]
+    fn auto_tag_from_subject() {
+        let msg = Message {
+            subject: String::from("  Spring   Sale — 20% off "),
+            ..Default::default()
+        }
+        .auto_tag_from_subject(true);
+        assert_eq!(
+            param(&msg.params(), "o:tag"),
+            Some(&String::from("Spring Sale 20% off"))
+        );
+
+        let msg = Message {
+            subject: "x".repeat(200),
+            ..Default::default()
+        }
+        .auto_tag_from_subject(true);
+        assert_eq!(param(&msg.params(), "o:tag").map(String::len), Some(128));
+
+        let msg = Message {
+            subject: String::from("Spring Sale"),
+            options: vec![SendOptions::Tag("explicit".to_owned())],
+            ..Default::default()
+        }
+        .auto_tag_from_subject(true);
+        assert_eq!(
+            param(&msg.params(), "o:tag"),
+            Some(&String::from("explicit"))
+        );
+
+        let msg = Message {
+            subject: String::from("Spring Sale"),
+            ..Default::default()
+        };
+        assert_eq!(param(&msg.params(), "o:tag"), None);
+    }
+
+    #[test]
+    fn html_with_auto_text() {
+        let html = "<html><body><h1>Hello&nbsp;there</h1>\n<p>Fish &amp; chips\n  for <b>&lt;you&gt;</b> &#8212; &#x263A; &bogus; A&B</p></body></html>";
+        match MessageBody::html_with_auto_text(html) {
+            MessageBody::HtmlAndText(h, text) => {
+                assert_eq!(h, html);
+                assert_eq!(
+                    text,
+                    "Hello there Fish & chips for <you> \u{2014} \u{263A} &bogus; A&B"
+                );
+            }
+            _ => panic!("expected both html and text bodies"),
+        }
+    }
+
+    #[test]
+    fn auto_text_skips_style_and_script() {
+        let html = r#"<html><HEAD><title>Newsletter</title>
+<style type="text/css">body { color: #333; } p > b { font-weight: bold; }</style></HEAD>
+<body><header>Top</header><p>Hello <b>you</b></p><script>track("open");</script>
+<STYLE>.footer{display:none}</STYLE><p>Bye</p></body></html>"#;
+        match MessageBody::html_with_auto_text(html) {
+            MessageBody::HtmlAndText(_, text) => assert_eq!(text, "Top Hello you Bye"),
+            _ => panic!("expected both html and text bodies"),
+        }
+
+        assert_eq!(html_to_text("<p>Hi</p><style>p { margin: 0 }"), "Hi");
+    }
+
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    #[test]
+    fn template_variables() {
+        let mut template_variables = HashMap::new();
+        template_variables.insert("name".to_string(), json!("Tim"));
+        template_variables.insert("items".to_string(), json!([{"sku": "a-1", "qty": 2}]));
+        let msg = Message {
+            template: Some("receipt".to_string()),
+            template_variables,
+            ..Default::default()
+        };
+
+        let params = msg.params();
+        assert_eq!(param(&params, "template"), Some(&String::from("receipt")));
+        let variables: serde_json::Value =
+            serde_json::from_str(param(&params, "h:X-Mailgun-Variables").unwrap()).unwrap();
+        assert_eq!(
+            variables,
+            json!({"name": "Tim", "items": [{"sku": "a-1", "qty": 2}]})
+        );
+
+        let msg = Message {
+            template: Some("receipt".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(param(&msg.params(), "h:X-Mailgun-Variables"), None);
+    }
+
+    #[test]
+    fn dedupe_attachments() {
+        let logo = || Attachment {
+            name: String::from("logo.png"),
+            content: b"the same logo".to_vec(),
+            mime_type: String::from("image/png"),
+        };
+        let msg = Message {
+            attachments: vec![
+                logo(),
+                Attachment::eml("original", String::from("Subject: hi")),
+                logo(),
+            ],
+            inline: vec![logo(), logo()],
+            ..Default::default()
+        }
+        .dedupe_attachments();
+
+        let names: Vec<&str> = msg.attachments.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["logo.png", "original.eml"]);
+        assert_eq!(msg.inline.len(), 1);
+    }
+
+    #[test]
+    fn eml_attachment() {
+        let raw = String::from("From: a@b.com\r\nSubject: Hi\r\n\r\nBody");
+        let attachment = Attachment::eml("original", raw.clone());
+        assert_eq!(attachment.name, "original.eml");
+        assert_eq!(attachment.mime_type, "message/rfc822");
+        assert_eq!(attachment.content, raw.into_bytes());
+
+        let attachment = Attachment::eml("forwarded.eml", String::new());
+        assert_eq!(attachment.name, "forwarded.eml");
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn request_unauthorized() {
+        // invalid key & domain
+        let creds = Credentials::new(
+            "key-your_key_here_that-is-very-long_and-still-goes-on-and-on-and-on",
+            "aksdfa32undkjns.com",
+        );
+        let recipient = EmailAddress::address("timmy@aksdfa32undkjns.com");
+        let message = Message {
+            to: vec![recipient],
+            subject: "Test email".to_string(),
+            ..Default::default()
+        };
+        let sender = EmailAddress::name_address("Nick Testla", "nick@aksdfa32undkjns.com");
+
+        let res = send_email(&creds, &sender, message);
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().status(), Some(StatusCode::UNAUTHORIZED));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[ignore]
+    #[test]
+    fn actually_send_email() {
+        // if you want to try actually sending an email w/ your credentials add them to this test
+        // and run it.
+        let domain = "sandbox-some_numbers_here_probably.mailgun.org";
+        let key = "something-secret-something-safe";
+        let recipient = "foo@bar.com";
+
+        let creds = Credentials::new(key, domain);
+        let recipient = EmailAddress::address(recipient);
+        let message = Message {
+            to: vec![recipient],
+            subject: "Test email".to_string(),
+            body: MessageBody::Text(String::from(
+                "This email is from an mailgun_v3 automated test",
+            )),
+            ..Default::default()
+        };
+        let sender = EmailAddress::name_address("Nick Testla", &format!("mailgun_v3@{}", &domain));
 
         let res = send_email(&creds, &sender, message);
         assert!(res.is_ok(), "{:?}", &res);
     }
 
+    #[cfg(feature = "blocking")]
     #[test]
     fn test_send_with_request_builder() {
         let domain = "sandbox0123456789abcdef0123456789abcdef.mailgun.org";
         let key = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
         let recipient = "user@example.com";
 
-        let creds = Credentials::new(&key, &domain);
-        let recipient = EmailAddress::address(&recipient);
+        let creds = Credentials::new(key, domain);
+        let recipient = EmailAddress::address(recipient);
         let message = Message {
             to: vec![recipient],
             subject: "Test email".to_string(),
@@ -323,10 +1583,400 @@ mod tests {
         let res = send_with_request_builder(request_builder, &creds, &sender, message);
         assert!(res.is_ok(), "{:?}", &res);
     }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn send_uses_api_base() {
+        let key = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+        let base = format!("{}/eu/v3", mockito::server_url());
+        let creds = Credentials::with_base(base, key, "api-base.example.com");
+        let sender = EmailAddress::address("mailgun_v3@api-base.example.com");
+        let response = json!({"id": "<1@api-base.example.com>", "message": "Queued. Thank you."});
+
+        let m = mockito::mock("POST", "/eu/v3/api-base.example.com/messages")
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let res = send_email(&creds, &sender, Message::default());
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn send_attachment() {
+        let key = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+        let creds = Credentials::with_base(mockito::server_url(), key, "attachment.example.com");
+        let sender = EmailAddress::address("mailgun_v3@attachment.example.com");
+        let message = Message {
+            subject: String::from("Your report"),
+            attachments: vec![Attachment {
+                name: String::from("report.csv"),
+                content: b"id,total\n1,42\n".to_vec(),
+                mime_type: String::from("text/csv"),
+            }],
+            ..Default::default()
+        };
+        let response = json!({"id": "<1@attachment.example.com>", "message": "Queued. Thank you."});
+
+        let m = mockito::mock("POST", "/attachment.example.com/messages")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex(
+                    r#"name="attachment"; filename="report.csv"\r\nContent-Type: text/csv"#
+                        .to_string(),
+                ),
+                mockito::Matcher::Regex("id,total\n1,42".to_string()),
+                mockito::Matcher::Regex(r#"name="subject"\r\n\r\nYour report"#.to_string()),
+            ]))
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let res = send_email(&creds, &sender, message);
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn stored_message() {
+        let key = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+        let creds = Credentials::new(key, "stored.example.com");
+        let path = "/v3/domains/stored.example.com/messages/AgEFkMBaFQ==";
+        let url = format!("{}{}", mockito::server_url(), path);
+        let attachment_url = format!("{}/attachments/0", url);
+        let response = json!({
+            "sender": "bob@example.com",
+            "recipients": "support@stored.example.com",
+            "from": "Bob <bob@example.com>",
+            "subject": "Re: order 1234",
+            "body-plain": "Thanks!\n\n> On Monday you wrote:\n> Your order shipped",
+            "body-html": "<p>Thanks!</p>",
+            "stripped-text": "Thanks!",
+            "Message-Id": "<abc@example.com>",
+            "attachments": [{
+                "url": attachment_url,
+                "content-type": "image/png",
+                "name": "receipt.png",
+                "size": 2048
+            }]
+        });
+
+        let m = mockito::mock("GET", path)
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let res = get_stored_message(&creds, &url);
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+        let stored = res.unwrap();
+        assert_eq!(stored.sender, "bob@example.com");
+        assert_eq!(stored.subject, Some("Re: order 1234".to_string()));
+        assert_eq!(stored.stripped_text, Some("Thanks!".to_string()));
+        assert_eq!(stored.body_html, Some("<p>Thanks!</p>".to_string()));
+        assert_eq!(
+            stored.attachments,
+            vec![StoredAttachment {
+                url: attachment_url,
+                name: "receipt.png".to_string(),
+                content_type: "image/png".to_string(),
+                size: 2048,
+            }]
+        );
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn delete_scheduled() {
+        let key = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+        let creds = Credentials::with_base(mockito::server_url(), key, "scheduled.example.com");
+        let m = mockito::mock("DELETE", "/scheduled.example.com/messages/BAABAQ==")
+            .with_status(200)
+            .with_body(json!({"message": "Message has been deleted"}).to_string())
+            .create();
+        let res = delete_scheduled_message(&creds, "BAABAQ==");
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+        assert_eq!(res.unwrap().message, "Message has been deleted");
+
+        // storage keys are base64 and may contain a '/'
+        let m = mockito::mock("DELETE", "/scheduled.example.com/messages/BAAB%2FQ==")
+            .with_status(200)
+            .with_body(json!({"message": "Message has been deleted"}).to_string())
+            .create();
+        let res = delete_scheduled_message(&creds, "BAAB/Q==");
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn send_amp() {
+        let key = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+        let creds = Credentials::with_base(mockito::server_url(), key, "amp.example.com");
+        let sender = EmailAddress::address("mailgun_v3@amp.example.com");
+        let message = Message {
+            body: MessageBody::Amp {
+                amp: String::from("<html amp4email>carousel</html>"),
+                html: String::from("<p>fallback</p>"),
+                text: None,
+            },
+            ..Default::default()
+        };
+        let response = json!({"id": "<1@amp.example.com>", "message": "Queued. Thank you."});
+
+        let m = mockito::mock("POST", "/amp.example.com/messages")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex(
+                    r#"name="amp-html"\r\n\r\n<html amp4email>carousel</html>"#.to_string(),
+                ),
+                mockito::Matcher::Regex(r#"name="html"\r\n\r\n<p>fallback</p>"#.to_string()),
+            ]))
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let res = send_email(&creds, &sender, message);
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn send_inline_and_attachment() {
+        let key = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+        let creds = Credentials::with_base(mockito::server_url(), key, "inline.example.com");
+        let sender = EmailAddress::address("mailgun_v3@inline.example.com");
+        let message = Message {
+            body: MessageBody::Html(String::from(r#"<img src="cid:logo.png">"#)),
+            attachments: vec![Attachment {
+                name: String::from("invoice.pdf"),
+                content: b"%PDF-1.4".to_vec(),
+                mime_type: String::from("application/pdf"),
+            }],
+            inline: vec![Attachment {
+                name: String::from("logo.png"),
+                content: b"not really a png".to_vec(),
+                mime_type: String::from("image/png"),
+            }],
+            ..Default::default()
+        };
+        let response = json!({"id": "<1@inline.example.com>", "message": "Queued. Thank you."});
+
+        let m = mockito::mock("POST", "/inline.example.com/messages")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex(r#"name="attachment"; filename="invoice.pdf""#.to_string()),
+                mockito::Matcher::Regex(r#"name="inline"; filename="logo.png""#.to_string()),
+            ]))
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let res = send_email(&creds, &sender, message);
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn send_personalized_attachments() {
+        let key = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+        let creds = Credentials::with_base(mockito::server_url(), key, "personal.example.com");
+        let sender = EmailAddress::address("billing@personal.example.com");
+        let invoice = |name: &str| Message {
+            subject: String::from("Your invoice"),
+            attachments: vec![Attachment {
+                name: name.to_string(),
+                content: b"%PDF-1.4".to_vec(),
+                mime_type: String::from("application/pdf"),
+            }],
+            ..Default::default()
+        };
+        let response = json!({"id": "<1@personal.example.com>", "message": "Queued. Thank you."});
+
+        let first = mockito::mock("POST", "/personal.example.com/messages")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex(r#"name="to"\r\n\r\na@example.com"#.to_string()),
+                mockito::Matcher::Regex(r#"filename="invoice-a.pdf""#.to_string()),
+            ]))
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let second = mockito::mock("POST", "/personal.example.com/messages")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex(r#"name="to"\r\n\r\nb@example.com"#.to_string()),
+                mockito::Matcher::Regex(r#"filename="invoice-b.pdf""#.to_string()),
+            ]))
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+
+        let client = reqwest::blocking::Client::new();
+        let results = send_personalized(
+            &client,
+            &creds,
+            &sender,
+            vec![
+                (
+                    EmailAddress::address("a@example.com"),
+                    invoice("invoice-a.pdf"),
+                ),
+                (
+                    EmailAddress::address("b@example.com"),
+                    invoice("invoice-b.pdf"),
+                ),
+            ],
+        );
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(Result::is_ok), "{:?}", &results);
+        first.assert();
+        second.assert();
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn send_scheduled() {
+        let key = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+        let creds = Credentials::with_base(mockito::server_url(), key, "scheduled.example.com");
+        let sender = EmailAddress::address("mailgun_v3@scheduled.example.com");
+        let response = json!({"id": "<1@scheduled.example.com>", "message": "Queued. Thank you."});
+        let _m = mockito::mock("POST", "/scheduled.example.com/messages")
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+
+        let instant = Utc.with_ymd_and_hms(2030, 1, 2, 9, 30, 0).unwrap();
+        let message = Message {
+            options: vec![SendOptions::DeliveryTime(instant)],
+            ..Default::default()
+        };
+        let res = send_email(&creds, &sender, message).unwrap();
+        assert_eq!(res.scheduled_for(), Some(instant));
+        assert_eq!(res.status(), SendStatus::Scheduled(instant));
+
+        let res = send_email(&creds, &sender, Message::default()).unwrap();
+        assert_eq!(res.scheduled_for(), None);
+        assert_eq!(res.status(), SendStatus::Queued);
+
+        // responses can still be built by hand, e.g. for mocks
+        let mocked = SendResponse {
+            message: String::from("Queued. Thank you."),
+            id: String::from("<1@example.com>"),
+            ..Default::default()
+        };
+        assert_eq!(mocked.status(), SendStatus::Queued);
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn send_retries_rate_limit() {
+        let key = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+        let retry = crate::RetryConfig {
+            max_retries: 2,
+            base_delay: std::time::Duration::from_secs(60),
+            max_delay: std::time::Duration::from_secs(60),
+        };
+        let creds = Credentials::with_base(mockito::server_url(), key, "retry.example.com")
+            .with_retry(retry);
+        let sender = EmailAddress::address("mailgun_v3@retry.example.com");
+        let response = json!({"id": "<1@retry.example.com>", "message": "Queued. Thank you."});
+
+        let limited = mockito::mock("POST", "/retry.example.com/messages")
+            .with_status(429)
+            .with_header("retry-after", "0")
+            .with_body(json!({"message": "Too many requests"}).to_string())
+            .expect(1)
+            .create();
+        let queued = mockito::mock("POST", "/retry.example.com/messages")
+            .with_status(200)
+            .with_body(response.to_string())
+            .expect(1)
+            .create();
+        let res = send_email(&creds, &sender, Message::default());
+        assert!(res.is_ok(), "{:?}", &res);
+        limited.assert();
+        queued.assert();
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn send_does_not_retry_client_errors() {
+        let key = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+        let retry = crate::RetryConfig {
+            max_retries: 2,
+            base_delay: std::time::Duration::from_millis(1),
+            max_delay: std::time::Duration::from_millis(1),
+        };
+        let creds = Credentials::with_base(mockito::server_url(), key, "no-retry.example.com")
+            .with_retry(retry);
+        let sender = EmailAddress::address("mailgun_v3@no-retry.example.com");
+
+        let m = mockito::mock("POST", "/no-retry.example.com/messages")
+            .with_status(401)
+            .with_body("Forbidden")
+            .expect(1)
+            .create();
+        let res = send_email(&creds, &sender, Message::default());
+        assert_eq!(res.unwrap_err().status(), Some(StatusCode::UNAUTHORIZED));
+        m.assert();
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn send_rejected() {
+        let key = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+        let creds = Credentials::with_base(mockito::server_url(), key, "rejected.example.com");
+        let sender = EmailAddress::address("mailgun_v3@rejected.example.com");
+        let response = json!({"message": "'to' parameter is not a valid address"});
+
+        let _m = mockito::mock("POST", "/rejected.example.com/messages")
+            .with_status(400)
+            .with_body(response.to_string())
+            .create();
+        let res = send_email(&creds, &sender, Message::default());
+        match res {
+            Err(crate::MailgunError::InvalidParameter { param, message }) => {
+                assert_eq!(param, "to");
+                assert_eq!(message, "'to' parameter is not a valid address");
+            }
+            _ => panic!("expected an invalid parameter error, got {:?}", res),
+        }
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn user_agent() {
+        let key = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+        let creds = Credentials::with_base(mockito::server_url(), key, "user-agent.example.com");
+        let sender = EmailAddress::address("mailgun_v3@user-agent.example.com");
+        let uri = format!("/{}/{}", creds.domain, MESSAGES_ENDPOINT);
+        let response = json!({"id": "<1@user-agent.example.com>", "message": "Queued. Thank you."});
+
+        let m = mockito::mock("POST", uri.as_str())
+            .match_header("user-agent", crate::USER_AGENT)
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let res = send_email(&creds, &sender, Message::default());
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+
+        let m = mockito::mock("POST", uri.as_str())
+            .match_header("user-agent", "my-app/1.0")
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let client = reqwest::blocking::Client::builder()
+            .user_agent("my-app/1.0")
+            .build()
+            .unwrap();
+        let res = send_with_client(&client, &creds, &sender, Message::default());
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+    }
 }
 
+#[cfg(feature = "async")]
 pub mod async_impl {
     use super::*;
+    use crate::{async_client, check_status_async};
 
     /// Sends a single email from the specified sender address
     /// [API docs](https://documentation.mailgun.com/en/latest/api-sending.html#sending)
@@ -335,11 +1985,12 @@ pub mod async_impl {
         sender: &EmailAddress,
         msg: Message,
     ) -> MailgunResult<SendResponse> {
-        let client = reqwest::Client::new();
+        let client = async_client(creds)?;
         send_with_client(&client, creds, sender, msg).await
     }
 
-    /// Same as `send_email` but with an externally managed client
+    /// Same as `send_email` but with an externally managed client.
+    /// Retries rate limited and failed sends if the credentials have a `RetryConfig`
     pub async fn send_with_client(
         client: &reqwest::Client,
         creds: &Credentials,
@@ -347,10 +1998,37 @@ pub mod async_impl {
         msg: Message,
     ) -> MailgunResult<SendResponse> {
         let url = format!("{}/{}/{}", creds.api_base, creds.domain, MESSAGES_ENDPOINT);
+        if let Some(retry) = creds.retry {
+            for attempt in 0..retry.max_retries {
+                let request_builder = client.post(&url);
+                match send_with_request_builder(request_builder, creds, sender, msg.clone()).await {
+                    Err(err) if err.is_retryable() => {
+                        tokio::time::sleep(retry.delay(attempt, &err)).await
+                    }
+                    res => return res,
+                }
+            }
+        }
         let request_builder = client.post(&url);
         send_with_request_builder(request_builder, creds, sender, msg).await
     }
 
+    /// Sends each message to its paired recipient as a separate request, see
+    /// `email::send_personalized`
+    pub async fn send_personalized(
+        client: &reqwest::Client,
+        creds: &Credentials,
+        sender: &EmailAddress,
+        messages: Vec<(EmailAddress, Message)>,
+    ) -> Vec<MailgunResult<SendResponse>> {
+        let mut results = Vec::with_capacity(messages.len());
+        for (recipient, mut msg) in messages {
+            msg.to = vec![recipient];
+            results.push(send_with_client(client, creds, sender, msg).await);
+        }
+        results
+    }
+
     /// Same as `send_email` but with an externally managed request builder.
     /// Use this in case you want to send the mails to a custom API endpoint, e.g. for testing.
     pub async fn send_with_request_builder(
@@ -360,7 +2038,13 @@ pub mod async_impl {
         msg: Message,
     ) -> MailgunResult<SendResponse> {
         let mut params = msg.params();
-        params.insert("from".to_string(), sender.to_string());
+        params.push(("from".to_string(), sender.to_string()));
+        let scheduled_for = msg.delivery_time();
+        let recipient = params
+            .iter()
+            .find(|(key, _)| key == "to")
+            .map(|(_, to)| to.clone())
+            .unwrap_or_default();
 
         let mut form = reqwest::multipart::Form::new();
         for (key, value) in params {
@@ -381,27 +2065,21 @@ pub mod async_impl {
             form = form.part("inline", file_part);
         }
         //add message content
-        match msg.body {
-            MessageBody::Text(text) => {
-                form = form.text("text", text);
-            }
-            MessageBody::Html(html) => {
-                form = form.text("html", html);
-            }
-            MessageBody::HtmlAndText(html, text) => {
-                form = form.text("text", text);
-                form = form.text("html", html);
-            }
+        for (key, value) in msg.body.into_params() {
+            form = form.text(key, value);
         }
 
         let res = request_builder
             .basic_auth("api", Some(creds.api_key.clone()))
             .multipart(form)
             .send()
-            .await?
-            .error_for_status()?;
+            .await?;
+        let res = check_status_async(res)
+            .await
+            .map_err(|err| err.restricted_to(recipient).invalid_param())?;
 
-        let parsed: SendResponse = res.json().await?;
+        let mut parsed: SendResponse = res.json().await?;
+        parsed.scheduled_for = scheduled_for;
         Ok(parsed)
     }
 
@@ -410,6 +2088,53 @@ pub mod async_impl {
         use super::*;
         use serde_json::json;
 
+        #[tokio::test]
+        async fn send_retries_unavailable() {
+            let key = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+            let retry = crate::RetryConfig {
+                max_retries: 2,
+                base_delay: std::time::Duration::from_millis(1),
+                max_delay: std::time::Duration::from_millis(10),
+            };
+            let creds =
+                Credentials::with_base(mockito::server_url(), key, "async-retry.example.com")
+                    .with_retry(retry);
+            let sender = EmailAddress::address("mailgun_v3@async-retry.example.com");
+            let response =
+                json!({"id": "<1@async-retry.example.com>", "message": "Queued. Thank you."});
+
+            let unavailable = mockito::mock("POST", "/async-retry.example.com/messages")
+                .with_status(503)
+                .with_body("Service Unavailable")
+                .expect(1)
+                .create();
+            let queued = mockito::mock("POST", "/async-retry.example.com/messages")
+                .with_status(200)
+                .with_body(response.to_string())
+                .expect(1)
+                .create();
+            let res = send_email(&creds, &sender, Message::default()).await;
+            assert!(res.is_ok(), "{:?}", &res);
+            unavailable.assert();
+            queued.assert();
+
+            // a 502 may come after Mailgun accepted the message, so it isn't retried
+            let creds =
+                Credentials::with_base(mockito::server_url(), key, "async-no-retry.example.com")
+                    .with_retry(retry);
+            let bad_gateway = mockito::mock("POST", "/async-no-retry.example.com/messages")
+                .with_status(502)
+                .with_body("Bad Gateway")
+                .expect(1)
+                .create();
+            let res = send_email(&creds, &sender, Message::default()).await;
+            assert_eq!(
+                res.unwrap_err().status(),
+                Some(reqwest::StatusCode::BAD_GATEWAY)
+            );
+            bad_gateway.assert();
+        }
+
         #[ignore]
         #[tokio::test]
         async fn actually_send_email() {
@@ -419,8 +2144,8 @@ pub mod async_impl {
             let key = "something-secret-something-safe";
             let recipient = "foo@bar.com";
 
-            let creds = Credentials::new(&key, &domain);
-            let recipient = EmailAddress::address(&recipient);
+            let creds = Credentials::new(key, domain);
+            let recipient = EmailAddress::address(recipient);
             let message = Message {
                 to: vec![recipient],
                 subject: "Test email".to_string(),
@@ -443,8 +2168,8 @@ pub mod async_impl {
             let key = "something-secret-something-safe";
             let recipient = "foo@bar.com";
 
-            let creds = Credentials::new(&key, &domain);
-            let recipient = EmailAddress::address(&recipient);
+            let creds = Credentials::new(key, domain);
+            let recipient = EmailAddress::address(recipient);
             let message = Message {
                 to: vec![recipient],
                 subject: "Test email".to_string(),
@@ -468,8 +2193,8 @@ pub mod async_impl {
             let key = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
             let recipient = "user@example.com";
 
-            let creds = Credentials::new(&key, &domain);
-            let recipient = EmailAddress::address(&recipient);
+            let creds = Credentials::new(key, domain);
+            let recipient = EmailAddress::address(recipient);
             let message = Message {
                 to: vec![recipient],
                 subject: "Test email".to_string(),
@@ -0,0 +1,275 @@
+//! Query the events/logs API to find out what happened to sent messages
+
+use chrono::prelude::*;
+
+use crate::Paging;
+#[cfg(feature = "blocking")]
+use crate::{blocking_client, check_status, Credentials, MailgunResult};
+
+/// The kinds of events Mailgun records
+#[derive(Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Accepted,
+    Rejected,
+    Delivered,
+    Failed,
+    Opened,
+    Clicked,
+    Unsubscribed,
+    Complained,
+    Stored,
+    /// Anything this crate doesn't model yet. Can't be used as a filter, queries leave it
+    /// out
+    #[serde(other)]
+    Other,
+}
+
+impl EventKind {
+    /// The API name, `None` for `Other`
+    #[cfg(feature = "blocking")]
+    fn as_str(&self) -> Option<&'static str> {
+        use self::EventKind::*;
+        let name = match self {
+            Accepted => "accepted",
+            Rejected => "rejected",
+            Delivered => "delivered",
+            Failed => "failed",
+            Opened => "opened",
+            Clicked => "clicked",
+            Unsubscribed => "unsubscribed",
+            Complained => "complained",
+            Stored => "stored",
+            Other => return None,
+        };
+        Some(name)
+    }
+}
+
+/// Filters for `get_events`, leave a field as `None` to not filter on it
+#[derive(Default)]
+pub struct EventQuery {
+    pub begin: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub event: Option<EventKind>,
+    pub recipient: Option<String>,
+    pub message_id: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl EventQuery {
+    #[cfg(feature = "blocking")]
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = Vec::new();
+        if let Some(begin) = self.begin {
+            params.push(("begin", begin.to_rfc2822()));
+        }
+        if let Some(end) = self.end {
+            params.push(("end", end.to_rfc2822()));
+        }
+        if let Some(event) = self.event.as_ref().and_then(EventKind::as_str) {
+            params.push(("event", event.to_string()));
+        }
+        if let Some(recipient) = &self.recipient {
+            params.push(("recipient", recipient.clone()));
+        }
+        if let Some(message_id) = &self.message_id {
+            params.push(("message-id", message_id.clone()));
+        }
+        if let Some(limit) = self.limit {
+            params.push(("limit", limit.to_string()));
+        }
+        params
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EventHeaders {
+    #[serde(rename = "message-id")]
+    pub message_id: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub subject: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EventMessage {
+    pub headers: EventHeaders,
+}
+
+/// A single entry from the event log
+#[derive(Deserialize, Debug)]
+pub struct Event {
+    pub id: String,
+    pub event: EventKind,
+    /// Seconds since the epoch
+    pub timestamp: f64,
+    pub recipient: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub reason: Option<String>,
+    pub message: Option<EventMessage>,
+}
+
+/// A page of events, follow `paging.next` with `get_events_page` to walk a whole time window
+#[derive(Deserialize, Debug)]
+pub struct EventsResponse {
+    pub items: Vec<Event>,
+    pub paging: Paging,
+}
+
+#[cfg(feature = "blocking")]
+const EVENTS_ENDPOINT: &str = "events";
+
+// curl -s --user 'api:YOUR_API_KEY' -G \
+//     https://api.mailgun.net/v3/YOUR_DOMAIN_NAME/events \
+//     --data-urlencode begin='Fri, 3 May 2013 09:00:00 -0000' \
+//     --data-urlencode event='failed'
+/// Fetch the first page of events matching `query`
+/// [API docs](https://documentation.mailgun.com/en/latest/api-events.html#events)
+#[cfg(feature = "blocking")]
+pub fn get_events(creds: &Credentials, query: &EventQuery) -> MailgunResult<EventsResponse> {
+    let client = blocking_client(creds)?;
+    get_events_with_client(&client, creds, query)
+}
+
+/// Same as `get_events` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn get_events_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    query: &EventQuery,
+) -> MailgunResult<EventsResponse> {
+    let url = format!("{}/{}/{}", creds.api_base, creds.domain, EVENTS_ENDPOINT);
+    let request_builder = client.get(url).query(&query.params());
+    get_events_with_request_builder(request_builder, creds)
+}
+
+/// Fetch the page of events at a `Paging` cursor URL
+#[cfg(feature = "blocking")]
+pub fn get_events_page(creds: &Credentials, url: &str) -> MailgunResult<EventsResponse> {
+    let client = blocking_client(creds)?;
+    get_events_page_with_client(&client, creds, url)
+}
+
+/// Same as `get_events_page` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn get_events_page_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    url: &str,
+) -> MailgunResult<EventsResponse> {
+    get_events_with_request_builder(client.get(url), creds)
+}
+
+/// Same as `get_events` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn get_events_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+) -> MailgunResult<EventsResponse> {
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: EventsResponse = res.json()?;
+    Ok(parsed)
+}
+
+#[cfg(all(test, feature = "blocking"))]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+    use serde_json::json;
+
+    const KEY: &str = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+
+    #[test]
+    fn query_filters() {
+        let creds = Credentials::with_base(mockito::server_url(), KEY, "events.example.com");
+        let next = format!(
+            "{}/events.example.com/events/W3siYSI6IGZhbHNlfV0=",
+            mockito::server_url()
+        );
+        let response = json!({
+            "items": [{
+                "id": "czsjqFATSlC3QtAK-C80nw",
+                "event": "failed",
+                "timestamp": 1376325780.160809,
+                "recipient": "tim@example.com",
+                "tags": ["welcome"],
+                "reason": "bounce",
+                "message": {
+                    "headers": {
+                        "message-id": "20130812164300.28108.52546@events.example.com",
+                        "from": "Sender <sender@events.example.com>",
+                        "to": "tim@example.com",
+                        "subject": "Hello"
+                    }
+                }
+            }, {
+                "id": "a1b2c3",
+                "event": "list_member_uploaded",
+                "timestamp": 1376325781.0
+            }],
+            "paging": {"first": null, "next": next, "previous": null, "last": null}
+        });
+
+        let m = mockito::mock("GET", "/events.example.com/events")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("begin".into(), "Fri, 3 May 2013 09:00:00 +0000".into()),
+                Matcher::UrlEncoded("end".into(), "Sat, 4 May 2013 09:00:00 +0000".into()),
+                Matcher::UrlEncoded("event".into(), "failed".into()),
+                Matcher::UrlEncoded("recipient".into(), "tim@example.com".into()),
+                Matcher::UrlEncoded(
+                    "message-id".into(),
+                    "20130812164300.28108.52546@events.example.com".into(),
+                ),
+                Matcher::UrlEncoded("limit".into(), "50".into()),
+            ]))
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let query = EventQuery {
+            begin: Some(Utc.with_ymd_and_hms(2013, 5, 3, 9, 0, 0).unwrap()),
+            end: Some(Utc.with_ymd_and_hms(2013, 5, 4, 9, 0, 0).unwrap()),
+            event: Some(EventKind::Failed),
+            recipient: Some("tim@example.com".to_string()),
+            message_id: Some("20130812164300.28108.52546@events.example.com".to_string()),
+            limit: Some(50),
+        };
+        let res = get_events(&creds, &query);
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+
+        let page = res.unwrap();
+        assert_eq!(page.items[0].event, EventKind::Failed);
+        assert_eq!(page.items[0].tags, vec!["welcome".to_string()]);
+        assert_eq!(
+            page.items[0].message.as_ref().unwrap().headers.message_id,
+            Some("20130812164300.28108.52546@events.example.com".to_string())
+        );
+        assert_eq!(page.items[1].event, EventKind::Other);
+
+        let m = mockito::mock("GET", "/events.example.com/events/W3siYSI6IGZhbHNlfV0=")
+            .with_status(200)
+            .with_body(json!({"items": [], "paging": {}}).to_string())
+            .create();
+        let res = get_events_page(&creds, page.paging.next.as_ref().unwrap());
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+        assert!(res.unwrap().items.is_empty());
+    }
+
+    #[test]
+    fn other_kind_is_not_a_filter() {
+        let query = EventQuery {
+            event: Some(EventKind::Other),
+            limit: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(query.params(), vec![("limit", "10".to_string())]);
+    }
+}
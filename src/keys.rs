@@ -0,0 +1,170 @@
+//! List and delete API keys, for automating key rotation. Keys live under Mailgun's `/v1`
+//! API, so the `/v3` suffix of `Credentials::api_base` is swapped out for these calls.
+//!
+//! Managing keys needs an account level key with the `admin` role, domain sending keys are
+//! rejected with a 401
+
+#[cfg(feature = "blocking")]
+use crate::{blocking_client, check_status, versioned_base, Credentials, MailgunResult};
+
+/// An API key, without its secret. Mailgun only returns the secret when a key is created
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ApiKey {
+    pub id: String,
+    pub description: Option<String>,
+    pub created_at: String,
+    /// `user`, `domain` or `web`
+    pub kind: String,
+    pub role: Option<String>,
+    /// Set for `domain` keys
+    pub domain_name: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct KeysResponse {
+    pub items: Vec<ApiKey>,
+    pub total_count: u64,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct DeleteKeyResponse {
+    pub message: String,
+}
+
+#[cfg(feature = "blocking")]
+const KEYS_ENDPOINT: &str = "keys";
+
+#[cfg(feature = "blocking")]
+fn keys_url(creds: &Credentials) -> String {
+    format!("{}/{}", versioned_base(creds, "v1"), KEYS_ENDPOINT)
+}
+
+// curl -s --user 'api:YOUR_API_KEY' https://api.mailgun.net/v1/keys
+/// List the account's API keys
+/// [API docs](https://documentation.mailgun.com/docs/mailgun/api-reference/openapi-final/tag/Keys/)
+#[cfg(feature = "blocking")]
+pub fn list_keys(creds: &Credentials) -> MailgunResult<KeysResponse> {
+    let client = blocking_client(creds)?;
+    list_keys_with_client(&client, creds)
+}
+
+/// Same as `list_keys` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn list_keys_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+) -> MailgunResult<KeysResponse> {
+    let request_builder = client.get(keys_url(creds));
+    list_keys_with_request_builder(request_builder, creds)
+}
+
+/// Same as `list_keys` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn list_keys_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+) -> MailgunResult<KeysResponse> {
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: KeysResponse = res.json()?;
+    Ok(parsed)
+}
+
+// curl -s --user 'api:YOUR_API_KEY' -X DELETE https://api.mailgun.net/v1/keys/KEY_ID
+/// Delete a key by its `id`, requests using it fail from then on
+/// [API docs](https://documentation.mailgun.com/docs/mailgun/api-reference/openapi-final/tag/Keys/)
+#[cfg(feature = "blocking")]
+pub fn delete_key(creds: &Credentials, id: &str) -> MailgunResult<DeleteKeyResponse> {
+    let client = blocking_client(creds)?;
+    delete_key_with_client(&client, creds, id)
+}
+
+/// Same as `delete_key` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn delete_key_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    id: &str,
+) -> MailgunResult<DeleteKeyResponse> {
+    let url = format!("{}/{}", keys_url(creds), id);
+    let request_builder = client.delete(url);
+    delete_key_with_request_builder(request_builder, creds)
+}
+
+/// Same as `delete_key` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn delete_key_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+) -> MailgunResult<DeleteKeyResponse> {
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: DeleteKeyResponse = res.json()?;
+    Ok(parsed)
+}
+
+#[cfg(all(test, feature = "blocking"))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const KEY: &str = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+
+    #[test]
+    fn list_and_delete_keys() {
+        let base = format!("{}/keys-test/v3", mockito::server_url());
+        let creds = Credentials::with_base(base, KEY, "keys.example.com");
+        let response = json!({
+            "items": [{
+                "id": "a1b2c3",
+                "description": "ci sending key",
+                "created_at": "2024-01-02T03:04:05.000Z",
+                "updated_at": "2024-01-02T03:04:05.000Z",
+                "kind": "domain",
+                "role": "sending",
+                "domain_name": "keys.example.com",
+                "requestor": "ops@example.com",
+                "user_name": "ops"
+            }],
+            "total_count": 1
+        });
+
+        let list = mockito::mock("GET", "/keys-test/v1/keys")
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let res = list_keys(&creds);
+        assert!(res.is_ok(), "{:?}", &res);
+        list.assert();
+        let keys = res.unwrap();
+        assert_eq!(keys.total_count, 1);
+        assert_eq!(
+            keys.items,
+            vec![ApiKey {
+                id: "a1b2c3".to_string(),
+                description: Some("ci sending key".to_string()),
+                created_at: "2024-01-02T03:04:05.000Z".to_string(),
+                kind: "domain".to_string(),
+                role: Some("sending".to_string()),
+                domain_name: Some("keys.example.com".to_string()),
+            }]
+        );
+
+        let delete = mockito::mock("DELETE", "/keys-test/v1/keys/a1b2c3")
+            .with_status(200)
+            .with_body(json!({"message": "key deleted"}).to_string())
+            .create();
+        let res = delete_key(&creds, &keys.items[0].id);
+        assert!(res.is_ok(), "{:?}", &res);
+        delete.assert();
+        assert_eq!(res.unwrap().message, "key deleted");
+    }
+}
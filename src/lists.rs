@@ -0,0 +1,387 @@
+//! Manage mailing lists. These live at the account root (`/lists`) rather than under the
+//! sending domain, so `Credentials::domain` is ignored here
+
+use std::collections::HashMap;
+
+use crate::Paging;
+#[cfg(feature = "blocking")]
+use crate::{api_url, blocking_client, check_status, Credentials, MailgunResult};
+
+/// A mailing list, addressed by its own email address
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct MailingList {
+    pub address: String,
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub members_count: u64,
+    pub created_at: Option<String>,
+    pub access_level: Option<String>,
+}
+
+/// A page of the account's mailing lists
+#[derive(Deserialize, Debug)]
+pub struct ListsResponse {
+    pub items: Vec<MailingList>,
+    pub paging: Paging,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ListResponse {
+    pub message: String,
+    pub list: MailingList,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DeleteListResponse {
+    pub address: String,
+    pub message: String,
+}
+
+/// A subscriber of a mailing list. `vars` show up as `%recipient.key%` in list sends
+#[derive(Deserialize, Debug, Default, PartialEq)]
+pub struct ListMember {
+    pub address: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub vars: HashMap<String, serde_json::Value>,
+    pub subscribed: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MemberResponse {
+    pub message: String,
+    pub member: ListMember,
+}
+
+#[cfg(feature = "blocking")]
+const LISTS_ENDPOINT: &str = "lists";
+
+// curl -s --user 'api:YOUR_API_KEY' \
+//     https://api.mailgun.net/v3/lists \
+//     -F address='LIST@YOUR_DOMAIN_NAME' \
+//     -F name='Developers' \
+//     -F description='Mailgun developers list'
+/// Create a new mailing list
+/// [API docs](https://documentation.mailgun.com/en/latest/api-mailinglists.html#mailing-lists)
+#[cfg(feature = "blocking")]
+pub fn create_list(
+    creds: &Credentials,
+    address: &str,
+    name: &str,
+    description: &str,
+) -> MailgunResult<ListResponse> {
+    let client = blocking_client(creds)?;
+    create_list_with_client(&client, creds, address, name, description)
+}
+
+/// Same as `create_list` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn create_list_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    address: &str,
+    name: &str,
+    description: &str,
+) -> MailgunResult<ListResponse> {
+    let url = format!("{}/{}", creds.api_base, LISTS_ENDPOINT);
+    let request_builder = client.post(url);
+    create_list_with_request_builder(request_builder, creds, address, name, description)
+}
+
+/// Same as `create_list` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn create_list_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+    address: &str,
+    name: &str,
+    description: &str,
+) -> MailgunResult<ListResponse> {
+    let mut params = HashMap::new();
+    params.insert("address", address);
+    params.insert("name", name);
+    params.insert("description", description);
+
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .form(&params)
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: ListResponse = res.json()?;
+    Ok(parsed)
+}
+
+// curl -s --user 'api:YOUR_API_KEY' -G \
+//     https://api.mailgun.net/v3/lists/pages
+/// Fetch the first page of mailing lists, follow `paging.next` with `get_lists_page`
+/// [API docs](https://documentation.mailgun.com/en/latest/api-mailinglists.html#mailing-lists)
+#[cfg(feature = "blocking")]
+pub fn get_lists(creds: &Credentials, limit: Option<usize>) -> MailgunResult<ListsResponse> {
+    let client = blocking_client(creds)?;
+    get_lists_with_client(&client, creds, limit)
+}
+
+/// Same as `get_lists` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn get_lists_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    limit: Option<usize>,
+) -> MailgunResult<ListsResponse> {
+    let url = format!("{}/{}/pages", creds.api_base, LISTS_ENDPOINT);
+    let mut request_builder = client.get(url);
+    if let Some(limit) = limit {
+        request_builder = request_builder.query(&[("limit", limit)]);
+    }
+    get_lists_with_request_builder(request_builder, creds)
+}
+
+/// Fetch the page of mailing lists at a `Paging` cursor URL
+#[cfg(feature = "blocking")]
+pub fn get_lists_page(creds: &Credentials, url: &str) -> MailgunResult<ListsResponse> {
+    let client = blocking_client(creds)?;
+    get_lists_with_request_builder(client.get(url), creds)
+}
+
+/// Same as `get_lists` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn get_lists_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+) -> MailgunResult<ListsResponse> {
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: ListsResponse = res.json()?;
+    Ok(parsed)
+}
+
+// curl -s --user 'api:YOUR_API_KEY' \
+//     https://api.mailgun.net/v3/lists/LIST@YOUR_DOMAIN_NAME/members \
+//     -F subscribed=True \
+//     -F address='bar@example.com' \
+//     -F name='Bob Bar' \
+//     -F vars='{"age": 26}'
+/// Add `member` to the list at `list_address`
+/// [API docs](https://documentation.mailgun.com/en/latest/api-mailinglists.html#mailing-lists)
+#[cfg(feature = "blocking")]
+pub fn add_list_member(
+    creds: &Credentials,
+    list_address: &str,
+    member: &ListMember,
+) -> MailgunResult<MemberResponse> {
+    let client = blocking_client(creds)?;
+    add_list_member_with_client(&client, creds, list_address, member)
+}
+
+/// Same as `add_list_member` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn add_list_member_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    list_address: &str,
+    member: &ListMember,
+) -> MailgunResult<MemberResponse> {
+    let url = api_url(&creds.api_base, &[LISTS_ENDPOINT, list_address, "members"])?;
+    let request_builder = client.post(url);
+    add_list_member_with_request_builder(request_builder, creds, member)
+}
+
+/// Same as `add_list_member` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn add_list_member_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+    member: &ListMember,
+) -> MailgunResult<MemberResponse> {
+    let mut params = HashMap::new();
+    params.insert("address", member.address.clone());
+    if let Some(name) = &member.name {
+        params.insert("name", name.clone());
+    }
+    if !member.vars.is_empty() {
+        let vars = serde_json::to_string(&member.vars).expect("string keyed maps always serialize");
+        params.insert("vars", vars);
+    }
+    let subscribed = if member.subscribed { "yes" } else { "no" };
+    params.insert("subscribed", subscribed.to_string());
+
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .form(&params)
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: MemberResponse = res.json()?;
+    Ok(parsed)
+}
+
+// curl -s --user 'api:YOUR_API_KEY' -X DELETE \
+//     https://api.mailgun.net/v3/lists/LIST@YOUR_DOMAIN_NAME
+/// Delete the list at `address` along with all of its members
+/// [API docs](https://documentation.mailgun.com/en/latest/api-mailinglists.html#mailing-lists)
+#[cfg(feature = "blocking")]
+pub fn delete_list(creds: &Credentials, address: &str) -> MailgunResult<DeleteListResponse> {
+    let client = blocking_client(creds)?;
+    delete_list_with_client(&client, creds, address)
+}
+
+/// Same as `delete_list` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn delete_list_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    address: &str,
+) -> MailgunResult<DeleteListResponse> {
+    let url = api_url(&creds.api_base, &[LISTS_ENDPOINT, address])?;
+    let request_builder = client.delete(url);
+    delete_list_with_request_builder(request_builder, creds)
+}
+
+/// Same as `delete_list` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn delete_list_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+) -> MailgunResult<DeleteListResponse> {
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: DeleteListResponse = res.json()?;
+    Ok(parsed)
+}
+
+#[cfg(all(test, feature = "blocking"))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const KEY: &str = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+
+    fn creds() -> Credentials {
+        let base = format!("{}/v3", mockito::server_url());
+        Credentials::with_base(base, KEY, "sending.example.com")
+    }
+
+    #[test]
+    fn create_and_list() {
+        let list = json!({
+            "address": "devs@lists.example.com",
+            "name": "Developers",
+            "description": "Mailgun developers list",
+            "members_count": 0,
+            "created_at": "Tue, 06 Mar 2012 05:44:45 GMT",
+            "access_level": "readonly"
+        });
+
+        let created = mockito::mock("POST", "/v3/lists")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("address".into(), "devs@lists.example.com".into()),
+                mockito::Matcher::UrlEncoded("name".into(), "Developers".into()),
+                mockito::Matcher::UrlEncoded(
+                    "description".into(),
+                    "Mailgun developers list".into(),
+                ),
+            ]))
+            .with_status(200)
+            .with_body(
+                json!({"message": "Mailing list has been created", "list": list}).to_string(),
+            )
+            .create();
+        let res = create_list(
+            &creds(),
+            "devs@lists.example.com",
+            "Developers",
+            "Mailgun developers list",
+        );
+        assert!(res.is_ok(), "{:?}", &res);
+        created.assert();
+        assert_eq!(res.unwrap().list.name, "Developers");
+
+        let listed = mockito::mock("GET", "/v3/lists/pages")
+            .with_status(200)
+            .with_body(
+                json!({
+                    "items": [list],
+                    "paging": {"first": null, "next": null, "previous": null, "last": null}
+                })
+                .to_string(),
+            )
+            .create();
+        let res = get_lists(&creds(), None);
+        assert!(res.is_ok(), "{:?}", &res);
+        listed.assert();
+        assert_eq!(res.unwrap().items[0].address, "devs@lists.example.com");
+    }
+
+    #[test]
+    fn add_member() {
+        let mut vars = HashMap::new();
+        vars.insert("age".to_string(), json!(26));
+        let member = ListMember {
+            address: "bar@example.com".to_string(),
+            name: Some("Bob Bar".to_string()),
+            vars,
+            subscribed: true,
+        };
+        let response = json!({
+            "message": "Mailing list member has been created",
+            "member": {
+                "address": "bar@example.com",
+                "name": "Bob Bar",
+                "vars": {"age": 26},
+                "subscribed": true
+            }
+        });
+
+        let m = mockito::mock("POST", "/v3/lists/devs@lists.example.com/members")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("address".into(), "bar@example.com".into()),
+                mockito::Matcher::UrlEncoded("name".into(), "Bob Bar".into()),
+                mockito::Matcher::UrlEncoded("vars".into(), r#"{"age":26}"#.into()),
+                mockito::Matcher::UrlEncoded("subscribed".into(), "yes".into()),
+            ]))
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let res = add_list_member(&creds(), "devs@lists.example.com", &member);
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+        assert_eq!(res.unwrap().member, member);
+    }
+
+    #[test]
+    fn remove_list() {
+        let m = mockito::mock("DELETE", "/v3/lists/devs@lists.example.com")
+            .with_status(200)
+            .with_body(
+                json!({"address": "devs@lists.example.com", "message": "Mailing list has been removed"})
+                    .to_string(),
+            )
+            .create();
+        let res = delete_list(&creds(), "devs@lists.example.com");
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+
+        let m = mockito::mock("DELETE", "/v3/lists/ops%3Fall@lists.example.com")
+            .with_status(200)
+            .with_body(
+                json!({"address": "ops?all@lists.example.com", "message": "Mailing list has been removed"})
+                    .to_string(),
+            )
+            .create();
+        let res = delete_list(&creds(), "ops?all@lists.example.com");
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+    }
+}
@@ -0,0 +1,280 @@
+//! Aggregate sending statistics, bucketed by hour, day or month
+
+use chrono::prelude::*;
+
+#[cfg(feature = "blocking")]
+use crate::{blocking_client, check_status, Credentials, MailgunResult};
+
+/// Size of the time buckets stats are grouped into
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Resolution {
+    Hour,
+    Day,
+    Month,
+}
+
+impl Resolution {
+    #[cfg(feature = "blocking")]
+    fn as_str(&self) -> &'static str {
+        match self {
+            Resolution::Hour => "hour",
+            Resolution::Day => "day",
+            Resolution::Month => "month",
+        }
+    }
+}
+
+/// The event types `/stats/total` counts, one per `StatItem` field
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum StatEvent {
+    Accepted,
+    Delivered,
+    Failed,
+    Opened,
+    Clicked,
+    Unsubscribed,
+    Complained,
+    Stored,
+}
+
+impl StatEvent {
+    #[cfg(feature = "blocking")]
+    fn as_str(&self) -> &'static str {
+        use self::StatEvent::*;
+        match self {
+            Accepted => "accepted",
+            Delivered => "delivered",
+            Failed => "failed",
+            Opened => "opened",
+            Clicked => "clicked",
+            Unsubscribed => "unsubscribed",
+            Complained => "complained",
+            Stored => "stored",
+        }
+    }
+}
+
+/// What to fetch stats for. At least one event type is required, Mailgun defaults to the
+/// last 7 days at day resolution when the rest are left as `None`
+#[derive(Default)]
+pub struct StatsQuery {
+    pub events: Vec<StatEvent>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub resolution: Option<Resolution>,
+}
+
+impl StatsQuery {
+    #[cfg(feature = "blocking")]
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = Vec::new();
+        for event in &self.events {
+            params.push(("event", event.as_str().to_string()));
+        }
+        if let Some(start) = self.start {
+            params.push(("start", start.to_rfc2822()));
+        }
+        if let Some(end) = self.end {
+            params.push(("end", end.to_rfc2822()));
+        }
+        if let Some(resolution) = self.resolution {
+            params.push(("resolution", resolution.as_str().to_string()));
+        }
+        params
+    }
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct AcceptedStats {
+    pub incoming: u64,
+    pub outgoing: u64,
+    pub total: u64,
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct DeliveredStats {
+    pub smtp: u64,
+    pub http: u64,
+    pub total: u64,
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PermanentFailures {
+    pub bounce: u64,
+    pub delayed_bounce: u64,
+    pub suppress_bounce: u64,
+    pub suppress_unsubscribe: u64,
+    pub suppress_complaint: u64,
+    pub total: u64,
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct TemporaryFailures {
+    pub espblock: u64,
+    pub total: u64,
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct FailedStats {
+    pub permanent: PermanentFailures,
+    pub temporary: TemporaryFailures,
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct TotalStats {
+    pub total: u64,
+}
+
+/// Counts for a single time bucket, event types that weren't requested are `None`
+#[derive(Deserialize, Debug)]
+pub struct StatItem {
+    pub time: String,
+    pub accepted: Option<AcceptedStats>,
+    pub delivered: Option<DeliveredStats>,
+    pub failed: Option<FailedStats>,
+    pub opened: Option<TotalStats>,
+    pub clicked: Option<TotalStats>,
+    pub unsubscribed: Option<TotalStats>,
+    pub complained: Option<TotalStats>,
+    pub stored: Option<TotalStats>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StatsResponse {
+    pub start: String,
+    pub end: String,
+    pub resolution: String,
+    pub stats: Vec<StatItem>,
+}
+
+#[cfg(feature = "blocking")]
+const STATS_ENDPOINT: &str = "stats/total";
+
+// curl -s --user 'api:YOUR_API_KEY' -G \
+//     https://api.mailgun.net/v3/YOUR_DOMAIN_NAME/stats/total \
+//     -d event='accepted' \
+//     -d event='delivered' \
+//     -d duration='1m'
+/// Fetch the domain's total stats for the events in `query`
+/// [API docs](https://documentation.mailgun.com/en/latest/api-stats.html#stats)
+#[cfg(feature = "blocking")]
+pub fn get_stats(creds: &Credentials, query: &StatsQuery) -> MailgunResult<StatsResponse> {
+    let client = blocking_client(creds)?;
+    get_stats_with_client(&client, creds, query)
+}
+
+/// Same as `get_stats` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn get_stats_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    query: &StatsQuery,
+) -> MailgunResult<StatsResponse> {
+    let url = format!("{}/{}/{}", creds.api_base, creds.domain, STATS_ENDPOINT);
+    let request_builder = client.get(url).query(&query.params());
+    get_stats_with_request_builder(request_builder, creds)
+}
+
+/// Same as `get_stats` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn get_stats_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+) -> MailgunResult<StatsResponse> {
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: StatsResponse = res.json()?;
+    Ok(parsed)
+}
+
+#[cfg(all(test, feature = "blocking"))]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+    use serde_json::json;
+
+    const KEY: &str = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+
+    #[test]
+    fn total_stats() {
+        let creds = Credentials::with_base(mockito::server_url(), KEY, "stats.example.com");
+        let response = json!({
+            "start": "Tue, 14 Feb 2012 00:00:00 UTC",
+            "end": "Thu, 01 Mar 2012 00:00:00 UTC",
+            "resolution": "month",
+            "stats": [{
+                "time": "Wed, 01 Feb 2012 00:00:00 UTC",
+                "accepted": {"outgoing": 10, "incoming": 5, "total": 15},
+                "delivered": {"smtp": 15, "http": 5, "total": 20},
+                "failed": {
+                    "permanent": {
+                        "bounce": 4,
+                        "delayed-bounce": 1,
+                        "suppress-bounce": 1,
+                        "suppress-unsubscribe": 2,
+                        "suppress-complaint": 3,
+                        "total": 10
+                    },
+                    "temporary": {"espblock": 1}
+                },
+                "opened": {"total": 7}
+            }]
+        });
+
+        let m = mockito::mock("GET", "/stats.example.com/stats/total")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::Regex("event=accepted".into()),
+                Matcher::Regex("event=delivered".into()),
+                Matcher::Regex("event=failed".into()),
+                Matcher::Regex("event=opened".into()),
+                Matcher::UrlEncoded("resolution".into(), "month".into()),
+                Matcher::UrlEncoded("start".into(), "Tue, 14 Feb 2012 00:00:00 +0000".into()),
+            ]))
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let query = StatsQuery {
+            events: vec![
+                StatEvent::Accepted,
+                StatEvent::Delivered,
+                StatEvent::Failed,
+                StatEvent::Opened,
+            ],
+            start: Some(Utc.with_ymd_and_hms(2012, 2, 14, 0, 0, 0).unwrap()),
+            resolution: Some(Resolution::Month),
+            ..Default::default()
+        };
+        let res = get_stats(&creds, &query);
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+
+        let parsed = res.unwrap();
+        assert_eq!(parsed.resolution, "month");
+        let bucket = &parsed.stats[0];
+        assert_eq!(
+            bucket.accepted,
+            Some(AcceptedStats {
+                incoming: 5,
+                outgoing: 10,
+                total: 15
+            })
+        );
+        assert_eq!(bucket.delivered.as_ref().unwrap().http, 5);
+        let failed = bucket.failed.as_ref().unwrap();
+        assert_eq!(failed.permanent.delayed_bounce, 1);
+        assert_eq!(failed.permanent.total, 10);
+        assert_eq!(failed.temporary.espblock, 1);
+        assert_eq!(bucket.opened, Some(TotalStats { total: 7 }));
+        assert_eq!(bucket.clicked, None);
+    }
+}
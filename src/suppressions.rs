@@ -0,0 +1,486 @@
+//! Manage the addresses Mailgun won't deliver to (bounces, unsubscribes)
+
+#[cfg(feature = "blocking")]
+use std::collections::HashMap;
+
+use crate::Paging;
+#[cfg(feature = "blocking")]
+use crate::{api_url, blocking_client, check_status, Credentials, MailgunResult};
+
+/// An address that hard bounced, so Mailgun stops sending to it
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct Bounce {
+    pub address: String,
+    /// SMTP error code, e.g. `550`
+    pub code: String,
+    pub error: String,
+    pub created_at: String,
+}
+
+/// A page of the bounce list
+#[derive(Deserialize, Debug)]
+pub struct BouncesResponse {
+    pub items: Vec<Bounce>,
+    pub paging: Paging,
+}
+
+/// An address that unsubscribed, from everything or only from some tags
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct Unsubscribe {
+    pub address: String,
+    /// Tags the address unsubscribed from, `*` means all mail
+    pub tags: Option<Vec<String>>,
+    pub created_at: String,
+}
+
+/// A page of the unsubscribe list
+#[derive(Deserialize, Debug)]
+pub struct UnsubscribesResponse {
+    pub items: Vec<Unsubscribe>,
+    pub paging: Paging,
+}
+
+/// Mailgun's confirmation for suppression changes
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct SuppressionResponse {
+    pub message: String,
+}
+
+#[cfg(feature = "blocking")]
+const BOUNCES_ENDPOINT: &str = "bounces";
+#[cfg(feature = "blocking")]
+const UNSUBSCRIBES_ENDPOINT: &str = "unsubscribes";
+
+// curl -s --user 'api:YOUR_API_KEY' -G \
+//     https://api.mailgun.net/v3/YOUR_DOMAIN_NAME/bounces \
+//     -d limit=100
+/// Fetch the first page of the domain's bounce list, follow `paging.next` with `get_bounces_page`
+/// [API docs](https://documentation.mailgun.com/en/latest/api-suppressions.html#bounces)
+#[cfg(feature = "blocking")]
+pub fn get_bounces(creds: &Credentials, limit: Option<usize>) -> MailgunResult<BouncesResponse> {
+    let client = blocking_client(creds)?;
+    get_bounces_with_client(&client, creds, limit)
+}
+
+/// Same as `get_bounces` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn get_bounces_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    limit: Option<usize>,
+) -> MailgunResult<BouncesResponse> {
+    let url = format!("{}/{}/{}", creds.api_base, creds.domain, BOUNCES_ENDPOINT);
+    let mut request_builder = client.get(url);
+    if let Some(limit) = limit {
+        request_builder = request_builder.query(&[("limit", limit)]);
+    }
+    get_bounces_with_request_builder(request_builder, creds)
+}
+
+/// Fetch the page of bounces at a `Paging` cursor URL
+#[cfg(feature = "blocking")]
+pub fn get_bounces_page(creds: &Credentials, url: &str) -> MailgunResult<BouncesResponse> {
+    let client = blocking_client(creds)?;
+    get_bounces_with_request_builder(client.get(url), creds)
+}
+
+/// Same as `get_bounces` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn get_bounces_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+) -> MailgunResult<BouncesResponse> {
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: BouncesResponse = res.json()?;
+    Ok(parsed)
+}
+
+// curl -s --user 'api:YOUR_API_KEY' -X DELETE \
+//     https://api.mailgun.net/v3/YOUR_DOMAIN_NAME/bounces/foo@bar.com
+/// Remove an address from the bounce list so Mailgun will deliver to it again
+/// [API docs](https://documentation.mailgun.com/en/latest/api-suppressions.html#bounces)
+#[cfg(feature = "blocking")]
+pub fn delete_bounce(creds: &Credentials, address: &str) -> MailgunResult<SuppressionResponse> {
+    let client = blocking_client(creds)?;
+    delete_bounce_with_client(&client, creds, address)
+}
+
+/// Same as `delete_bounce` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn delete_bounce_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    address: &str,
+) -> MailgunResult<SuppressionResponse> {
+    let url = api_url(&creds.api_base, &[&creds.domain, BOUNCES_ENDPOINT, address])?;
+    let request_builder = client.delete(url);
+    delete_bounce_with_request_builder(request_builder, creds)
+}
+
+/// Same as `delete_bounce` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn delete_bounce_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+) -> MailgunResult<SuppressionResponse> {
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: SuppressionResponse = res.json()?;
+    Ok(parsed)
+}
+
+// curl -s --user 'api:YOUR_API_KEY' -G \
+//     https://api.mailgun.net/v3/YOUR_DOMAIN_NAME/unsubscribes
+/// Fetch the first page of the domain's unsubscribe list, follow `paging.next` with
+/// `get_unsubscribes_page`
+/// [API docs](https://documentation.mailgun.com/en/latest/api-suppressions.html#unsubscribes)
+#[cfg(feature = "blocking")]
+pub fn get_unsubscribes(
+    creds: &Credentials,
+    limit: Option<usize>,
+) -> MailgunResult<UnsubscribesResponse> {
+    let client = blocking_client(creds)?;
+    get_unsubscribes_with_client(&client, creds, limit)
+}
+
+/// Same as `get_unsubscribes` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn get_unsubscribes_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    limit: Option<usize>,
+) -> MailgunResult<UnsubscribesResponse> {
+    let url = format!(
+        "{}/{}/{}",
+        creds.api_base, creds.domain, UNSUBSCRIBES_ENDPOINT
+    );
+    let mut request_builder = client.get(url);
+    if let Some(limit) = limit {
+        request_builder = request_builder.query(&[("limit", limit)]);
+    }
+    get_unsubscribes_with_request_builder(request_builder, creds)
+}
+
+/// Fetch the page of unsubscribes at a `Paging` cursor URL
+#[cfg(feature = "blocking")]
+pub fn get_unsubscribes_page(
+    creds: &Credentials,
+    url: &str,
+) -> MailgunResult<UnsubscribesResponse> {
+    let client = blocking_client(creds)?;
+    get_unsubscribes_with_request_builder(client.get(url), creds)
+}
+
+/// Same as `get_unsubscribes` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn get_unsubscribes_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+) -> MailgunResult<UnsubscribesResponse> {
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: UnsubscribesResponse = res.json()?;
+    Ok(parsed)
+}
+
+// curl -s --user 'api:YOUR_API_KEY' \
+//     https://api.mailgun.net/v3/YOUR_DOMAIN_NAME/unsubscribes \
+//     -F address='bob@example.com' \
+//     -F tag='*'
+/// Unsubscribe an address, from everything or only from mail with `tag`
+/// [API docs](https://documentation.mailgun.com/en/latest/api-suppressions.html#unsubscribes)
+#[cfg(feature = "blocking")]
+pub fn create_unsubscribe(
+    creds: &Credentials,
+    address: &str,
+    tag: Option<&str>,
+) -> MailgunResult<SuppressionResponse> {
+    let client = blocking_client(creds)?;
+    create_unsubscribe_with_client(&client, creds, address, tag)
+}
+
+/// Same as `create_unsubscribe` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn create_unsubscribe_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    address: &str,
+    tag: Option<&str>,
+) -> MailgunResult<SuppressionResponse> {
+    let url = format!(
+        "{}/{}/{}",
+        creds.api_base, creds.domain, UNSUBSCRIBES_ENDPOINT
+    );
+    let request_builder = client.post(url);
+    create_unsubscribe_with_request_builder(request_builder, creds, address, tag)
+}
+
+/// Same as `create_unsubscribe` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn create_unsubscribe_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+    address: &str,
+    tag: Option<&str>,
+) -> MailgunResult<SuppressionResponse> {
+    let mut params = HashMap::new();
+    params.insert("address", address);
+    if let Some(tag) = tag {
+        params.insert("tag", tag);
+    }
+
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .form(&params)
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: SuppressionResponse = res.json()?;
+    Ok(parsed)
+}
+
+// curl -s --user 'api:YOUR_API_KEY' -X DELETE \
+//     https://api.mailgun.net/v3/YOUR_DOMAIN_NAME/unsubscribes/bob@example.com
+/// Remove an address from the unsubscribe list
+/// [API docs](https://documentation.mailgun.com/en/latest/api-suppressions.html#unsubscribes)
+#[cfg(feature = "blocking")]
+pub fn delete_unsubscribe(
+    creds: &Credentials,
+    address: &str,
+) -> MailgunResult<SuppressionResponse> {
+    let client = blocking_client(creds)?;
+    delete_unsubscribe_with_client(&client, creds, address)
+}
+
+/// Same as `delete_unsubscribe` but with an externally managed client
+#[cfg(feature = "blocking")]
+pub fn delete_unsubscribe_with_client(
+    client: &reqwest::blocking::Client,
+    creds: &Credentials,
+    address: &str,
+) -> MailgunResult<SuppressionResponse> {
+    let url = api_url(
+        &creds.api_base,
+        &[&creds.domain, UNSUBSCRIBES_ENDPOINT, address],
+    )?;
+    let request_builder = client.delete(url);
+    delete_unsubscribe_with_request_builder(request_builder, creds)
+}
+
+/// Same as `delete_unsubscribe` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn delete_unsubscribe_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+) -> MailgunResult<SuppressionResponse> {
+    let res = request_builder
+        .basic_auth("api", Some(creds.api_key.clone()))
+        .send()?;
+    let res = check_status(res)?;
+
+    let parsed: SuppressionResponse = res.json()?;
+    Ok(parsed)
+}
+
+#[cfg(all(test, feature = "blocking"))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const KEY: &str = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+
+    #[test]
+    fn list_bounces() {
+        let creds = Credentials::with_base(mockito::server_url(), KEY, "bounces.example.com");
+        let next = format!(
+            "{}/bounces.example.com/bounces?page=next&address=b@example.com&limit=1",
+            mockito::server_url()
+        );
+        let first_page = json!({
+            "items": [{
+                "address": "a@example.com",
+                "code": "550",
+                "error": "No such mailbox",
+                "created_at": "Fri, 21 Oct 2011 11:02:55 UTC"
+            }],
+            "paging": {
+                "first": "https://api.mailgun.net/v3/bounces.example.com/bounces?page=first",
+                "next": next,
+                "previous": null,
+                "last": "https://api.mailgun.net/v3/bounces.example.com/bounces?page=last"
+            }
+        });
+        let second_page = json!({
+            "items": [],
+            "paging": {"first": null, "next": null, "previous": null, "last": null}
+        });
+
+        let first = mockito::mock("GET", "/bounces.example.com/bounces")
+            .match_query(mockito::Matcher::UrlEncoded("limit".into(), "1".into()))
+            .with_status(200)
+            .with_body(first_page.to_string())
+            .create();
+        let res = get_bounces(&creds, Some(1));
+        assert!(res.is_ok(), "{:?}", &res);
+        let page = res.unwrap();
+        first.assert();
+        assert_eq!(
+            page.items,
+            vec![Bounce {
+                address: "a@example.com".to_string(),
+                code: "550".to_string(),
+                error: "No such mailbox".to_string(),
+                created_at: "Fri, 21 Oct 2011 11:02:55 UTC".to_string(),
+            }]
+        );
+
+        let second = mockito::mock("GET", "/bounces.example.com/bounces")
+            .match_query(mockito::Matcher::UrlEncoded("page".into(), "next".into()))
+            .with_status(200)
+            .with_body(second_page.to_string())
+            .create();
+        let res = get_bounces_page(&creds, page.paging.next.as_ref().unwrap());
+        assert!(res.is_ok(), "{:?}", &res);
+        second.assert();
+        assert!(res.unwrap().items.is_empty());
+    }
+
+    #[test]
+    fn remove_bounce() {
+        let creds = Credentials::with_base(mockito::server_url(), KEY, "bounces.example.com");
+        let response = json!({
+            "message": "Bounced address has been removed",
+            "address": "a@example.com"
+        });
+
+        let m = mockito::mock("DELETE", "/bounces.example.com/bounces/a@example.com")
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let res = delete_bounce(&creds, "a@example.com");
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+        assert_eq!(res.unwrap().message, "Bounced address has been removed");
+    }
+
+    #[test]
+    fn remove_bounce_escapes_address() {
+        let creds = Credentials::with_base(mockito::server_url(), KEY, "escape.example.com");
+        let m = mockito::mock(
+            "DELETE",
+            "/escape.example.com/bounces/a%25b%2Fc%3Fd%23e@example.com",
+        )
+        .with_status(200)
+        .with_body(json!({"message": "Bounced address has been removed"}).to_string())
+        .create();
+        let res = delete_bounce(&creds, "a%b/c?d#e@example.com");
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+    }
+
+    #[test]
+    fn list_unsubscribes() {
+        let creds = Credentials::with_base(mockito::server_url(), KEY, "unsubs.example.com");
+        let response = json!({
+            "items": [
+                {
+                    "address": "a@example.com",
+                    "tags": ["newsletter", "promo"],
+                    "created_at": "Fri, 21 Oct 2011 11:02:55 UTC"
+                },
+                {
+                    "address": "b@example.com",
+                    "created_at": "Sat, 22 Oct 2011 11:02:55 UTC"
+                }
+            ],
+            "paging": {"first": null, "next": null, "previous": null, "last": null}
+        });
+
+        let _m = mockito::mock("GET", "/unsubs.example.com/unsubscribes")
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let res = get_unsubscribes(&creds, None);
+        assert!(res.is_ok(), "{:?}", &res);
+        let items = res.unwrap().items;
+        assert_eq!(
+            items[0].tags,
+            Some(vec!["newsletter".to_string(), "promo".to_string()])
+        );
+        assert_eq!(items[1].address, "b@example.com");
+        assert_eq!(items[1].tags, None);
+    }
+
+    #[test]
+    fn add_unsubscribe() {
+        let creds = Credentials::with_base(mockito::server_url(), KEY, "unsubs.example.com");
+        let response = json!({
+            "message": "Address has been added to the unsubscribes table",
+            "address": "a@example.com"
+        });
+
+        let tagged = mockito::mock("POST", "/unsubs.example.com/unsubscribes")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("address".into(), "a@example.com".into()),
+                mockito::Matcher::UrlEncoded("tag".into(), "promo".into()),
+            ]))
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let res = create_unsubscribe(&creds, "a@example.com", Some("promo"));
+        assert!(res.is_ok(), "{:?}", &res);
+        tagged.assert();
+
+        let untagged = mockito::mock("POST", "/unsubs.example.com/unsubscribes")
+            .match_body("address=a%40example.com")
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let res = create_unsubscribe(&creds, "a@example.com", None);
+        assert!(res.is_ok(), "{:?}", &res);
+        untagged.assert();
+    }
+
+    #[test]
+    fn remove_unsubscribe() {
+        let creds = Credentials::with_base(mockito::server_url(), KEY, "unsubs.example.com");
+        let response = json!({
+            "message": "Unsubscribe event has been removed",
+            "address": "a@example.com"
+        });
+
+        let m = mockito::mock("DELETE", "/unsubs.example.com/unsubscribes/a@example.com")
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let res = delete_unsubscribe(&creds, "a@example.com");
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+
+        let m = mockito::mock(
+            "DELETE",
+            "/unsubs.example.com/unsubscribes/a%2Fb@example.com",
+        )
+        .with_status(200)
+        .with_body(response.to_string())
+        .create();
+        let res = delete_unsubscribe(&creds, "a/b@example.com");
+        assert!(res.is_ok(), "{:?}", &res);
+        m.assert();
+    }
+}
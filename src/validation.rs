@@ -1,17 +1,19 @@
 //! Validate emails through Mailgun, to reduce bounce rate, find typos, etc
 
-use reqwest;
+#[cfg(any(feature = "blocking", feature = "async"))]
 use std::collections::HashMap;
 
+#[cfg(feature = "blocking")]
+use crate::{blocking_client, check_status};
+#[cfg(any(feature = "blocking", feature = "async"))]
 use crate::{Credentials, MailgunResult};
 
-///! Returned for sucessfully parsed email addresses
-#[allow(unused)]
+/// Returned for sucessfully parsed email addresses
 #[derive(Deserialize, Debug)]
 pub struct EmailParts {
-    domain: String,
-    display_name: Option<String>,
-    local_part: String,
+    pub domain: String,
+    pub display_name: Option<String>,
+    pub local_part: String,
 }
 
 #[derive(Deserialize, Debug)]
@@ -25,6 +27,54 @@ pub struct ValidationResponse {
     pub reason: Option<String>,
 }
 
+impl ValidationResponse {
+    /// `reason` parsed into a `ValidationReason`, for branching on without string matching
+    pub fn reason_kind(&self) -> Option<ValidationReason> {
+        self.reason.as_deref().map(ValidationReason::from)
+    }
+}
+
+/// The documented values of `ValidationResponse.reason`
+#[derive(Debug, PartialEq, Clone)]
+pub enum ValidationReason {
+    MalformedAddress,
+    NoMx,
+    MailboxDoesNotExist,
+    MailboxIsDisposableAddress,
+    MailboxIsRoleAddress,
+    HighRiskDomain,
+    SubdomainMailer,
+    ImmatureDomain,
+    TldRisk,
+    CatchAll,
+    LongTermDisposable,
+    UnknownProvider,
+    /// A reason this crate doesn't know about yet
+    Unknown(String),
+}
+
+impl From<&str> for ValidationReason {
+    fn from(reason: &str) -> Self {
+        use self::ValidationReason::*;
+        match reason {
+            "no_mx" => NoMx,
+            "mailbox_does_not_exist" => MailboxDoesNotExist,
+            "mailbox_is_disposable_address" => MailboxIsDisposableAddress,
+            "mailbox_is_role_address" => MailboxIsRoleAddress,
+            "high_risk_domain" => HighRiskDomain,
+            "subdomain_mailer" => SubdomainMailer,
+            "immature_domain" => ImmatureDomain,
+            "tld_risk" => TldRisk,
+            "catch_all" => CatchAll,
+            "long_term_disposable" => LongTermDisposable,
+            "unknown_provider" => UnknownProvider,
+            _ if reason.starts_with("Malformed address") => MalformedAddress,
+            _ => Unknown(reason.to_string()),
+        }
+    }
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
 const VALIDATION_ENDPOINT: &str = "address/private/validate";
 
 // curl -G --user 'api:pubkey-5ogiflzbnjrljiky49qxsiozqef5jxp7' -G \
@@ -32,27 +82,40 @@ const VALIDATION_ENDPOINT: &str = "address/private/validate";
 //     --data-urlencode address='foo@mailgun.net'
 /// Validate an email using mailgun's validation service
 /// [API docs](https://documentation.mailgun.com/en/latest/api-email-validation.html#email-validation)
+#[cfg(feature = "blocking")]
 pub fn validate_email(creds: &Credentials, address: &str) -> MailgunResult<ValidationResponse> {
-    let client = reqwest::blocking::Client::new();
+    let client = blocking_client(creds)?;
     validate_email_with_client(&client, creds, address)
 }
 
 /// Same as `validate_email` but with an externally managed client
+#[cfg(feature = "blocking")]
 pub fn validate_email_with_client(
     client: &reqwest::blocking::Client,
     creds: &Credentials,
     address: &str,
 ) -> MailgunResult<ValidationResponse> {
     let url = format!("{}/{}", creds.api_base, VALIDATION_ENDPOINT);
+    let request_builder = client.get(url);
+    validate_email_with_request_builder(request_builder, creds, address)
+}
+
+/// Same as `validate_email` but with an externally managed request builder.
+/// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+#[cfg(feature = "blocking")]
+pub fn validate_email_with_request_builder(
+    request_builder: reqwest::blocking::RequestBuilder,
+    creds: &Credentials,
+    address: &str,
+) -> MailgunResult<ValidationResponse> {
     let mut params = HashMap::new();
     params.insert("address".to_string(), address);
 
-    let res = client
-        .get(url)
+    let res = request_builder
         .basic_auth("api", Some(creds.api_key.clone()))
         .form(&params)
-        .send()?
-        .error_for_status()?;
+        .send()?;
+    let res = check_status(res)?;
 
     let parsed: ValidationResponse = res.json()?;
     Ok(parsed)
@@ -61,13 +124,97 @@ pub fn validate_email_with_client(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn validation_reasons() {
+        assert_eq!(
+            ValidationReason::from("mailbox_does_not_exist"),
+            ValidationReason::MailboxDoesNotExist
+        );
+        assert_eq!(ValidationReason::from("no_mx"), ValidationReason::NoMx);
+        assert_eq!(
+            ValidationReason::from("mailbox_is_role_address"),
+            ValidationReason::MailboxIsRoleAddress
+        );
+        assert_eq!(
+            ValidationReason::from("Malformed address; failed parse with exception"),
+            ValidationReason::MalformedAddress
+        );
+        assert_eq!(
+            ValidationReason::from("something_new"),
+            ValidationReason::Unknown("something_new".to_string())
+        );
+
+        let parsed: ValidationResponse = serde_json::from_value(json!({
+            "address": "foo@example.com",
+            "did_you_mean": null,
+            "is_disposable_address": false,
+            "is_role_address": false,
+            "is_valid": false,
+            "parts": null,
+            "reason": "high_risk_domain"
+        }))
+        .unwrap();
+        assert_eq!(parsed.reason, Some("high_risk_domain".to_string()));
+        assert_eq!(parsed.reason_kind(), Some(ValidationReason::HighRiskDomain));
+    }
 
+    #[test]
+    fn email_parts() {
+        let parsed: ValidationResponse = serde_json::from_value(json!({
+            "address": "Tim Smith <Tim.Smith@Example.com>",
+            "did_you_mean": null,
+            "is_disposable_address": false,
+            "is_role_address": false,
+            "is_valid": true,
+            "parts": {
+                "display_name": "Tim Smith",
+                "domain": "Example.com",
+                "local_part": "Tim.Smith"
+            },
+            "reason": null
+        }))
+        .unwrap();
+        let parts = parsed.parts.unwrap();
+        assert_eq!(parts.local_part, "Tim.Smith");
+        assert_eq!(parts.domain, "Example.com");
+        assert_eq!(parts.display_name, Some("Tim Smith".to_string()));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn validate_uses_api_base() {
+        let key = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+        let base = format!("{}/eu/v3", mockito::server_url());
+        let creds = Credentials::with_base(base, key, "api-base.example.com");
+        let response = json!({
+            "address": "foo@example.com",
+            "did_you_mean": null,
+            "is_disposable_address": false,
+            "is_role_address": false,
+            "is_valid": true,
+            "parts": null,
+            "reason": null
+        });
+
+        let m = mockito::mock("GET", "/eu/v3/address/private/validate")
+            .with_status(200)
+            .with_body(response.to_string())
+            .create();
+        let res = validate_email(&creds, "foo@example.com");
+        assert!(res.is_ok(), "{:?}", &res);
+        assert!(res.unwrap().is_valid);
+        m.assert();
+    }
+
+    #[cfg(feature = "blocking")]
     #[ignore]
     #[test]
     fn run_validate_email() {
         // add your api key here to run the tests - accounts get 100 validations/month free
         let key = "something-secret-something-safe";
-        let creds = Credentials::new(&key, "not needed");
+        let creds = Credentials::new(key, "not needed");
 
         let res = validate_email(&creds, "james.earl.jones@gmail.com");
         assert!(res.is_ok(), "{:?}", &res);
@@ -80,3 +227,375 @@ mod tests {
         assert_eq!(parsed.reason, None);
     }
 }
+
+/// Validate whole lists at once. Mailgun processes uploaded lists in the background: create
+/// a job, poll `get_bulk_job_status` until it's `Uploaded`, then download the results.
+/// These live under Mailgun's `/v4` API, the `/v3` suffix of `Credentials::api_base` is
+/// swapped out for these calls
+pub mod bulk {
+    #[cfg(feature = "blocking")]
+    use crate::{blocking_client, check_status, versioned_base, Credentials, MailgunResult};
+
+    /// Where a bulk job is at, `Uploaded` means its results can be downloaded
+    #[derive(Deserialize, Debug, PartialEq, Clone)]
+    #[serde(rename_all = "lowercase")]
+    pub enum BulkJobStatus {
+        Created,
+        Uploading,
+        Processing,
+        Uploaded,
+        Failed,
+        #[serde(other)]
+        Other,
+    }
+
+    /// Presigned links to the job's results
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct BulkDownloadUrls {
+        pub csv: String,
+        pub json: String,
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct BulkJob {
+        pub id: String,
+        pub status: BulkJobStatus,
+        #[serde(default)]
+        pub quantity: u64,
+        #[serde(default)]
+        pub records_processed: u64,
+        /// Unix timestamp
+        pub created_at: Option<i64>,
+        /// Set once the job is `Uploaded`
+        pub download_url: Option<BulkDownloadUrls>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct CreateBulkJobResponse {
+        pub id: String,
+        pub message: String,
+    }
+
+    #[cfg(feature = "blocking")]
+    const BULK_ENDPOINT: &str = "address/validate/bulk";
+
+    #[cfg(feature = "blocking")]
+    fn bulk_url(creds: &Credentials, list_id: &str) -> String {
+        format!(
+            "{}/{}/{}",
+            versioned_base(creds, "v4"),
+            BULK_ENDPOINT,
+            list_id
+        )
+    }
+
+    /// One address per line under an `email` header
+    #[cfg(feature = "blocking")]
+    fn addresses_csv(addresses: &[&str]) -> String {
+        let mut csv = String::from("email\n");
+        for address in addresses {
+            csv.push_str(address.trim());
+            csv.push('\n');
+        }
+        csv
+    }
+
+    // curl -s --user 'api:YOUR_API_KEY' \
+    //     https://api.mailgun.net/v4/address/validate/bulk/LIST_ID \
+    //     -F 'file=@addresses.csv'
+    /// Upload `addresses` as a new bulk validation job named `list_id`
+    /// [API docs](https://documentation.mailgun.com/en/latest/api-email-validation.html#bulk-validation)
+    #[cfg(feature = "blocking")]
+    pub fn create_bulk_job(
+        creds: &Credentials,
+        list_id: &str,
+        addresses: &[&str],
+    ) -> MailgunResult<CreateBulkJobResponse> {
+        let client = blocking_client(creds)?;
+        create_bulk_job_with_client(&client, creds, list_id, addresses)
+    }
+
+    /// Same as `create_bulk_job` but with an externally managed client
+    #[cfg(feature = "blocking")]
+    pub fn create_bulk_job_with_client(
+        client: &reqwest::blocking::Client,
+        creds: &Credentials,
+        list_id: &str,
+        addresses: &[&str],
+    ) -> MailgunResult<CreateBulkJobResponse> {
+        let request_builder = client.post(bulk_url(creds, list_id));
+        create_bulk_job_with_request_builder(request_builder, creds, addresses)
+    }
+
+    /// Same as `create_bulk_job` but with an externally managed request builder.
+    /// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+    #[cfg(feature = "blocking")]
+    pub fn create_bulk_job_with_request_builder(
+        request_builder: reqwest::blocking::RequestBuilder,
+        creds: &Credentials,
+        addresses: &[&str],
+    ) -> MailgunResult<CreateBulkJobResponse> {
+        let file = reqwest::blocking::multipart::Part::text(addresses_csv(addresses))
+            .file_name("addresses.csv")
+            .mime_str("text/csv")?;
+        let form = reqwest::blocking::multipart::Form::new().part("file", file);
+        let res = request_builder
+            .basic_auth("api", Some(creds.api_key.clone()))
+            .multipart(form)
+            .send()?;
+        let res = check_status(res)?;
+
+        let parsed: CreateBulkJobResponse = res.json()?;
+        Ok(parsed)
+    }
+
+    // curl -s --user 'api:YOUR_API_KEY' \
+    //     https://api.mailgun.net/v4/address/validate/bulk/LIST_ID
+    /// Poll a bulk job's progress
+    /// [API docs](https://documentation.mailgun.com/en/latest/api-email-validation.html#bulk-validation)
+    #[cfg(feature = "blocking")]
+    pub fn get_bulk_job_status(creds: &Credentials, list_id: &str) -> MailgunResult<BulkJob> {
+        let client = blocking_client(creds)?;
+        get_bulk_job_status_with_client(&client, creds, list_id)
+    }
+
+    /// Same as `get_bulk_job_status` but with an externally managed client
+    #[cfg(feature = "blocking")]
+    pub fn get_bulk_job_status_with_client(
+        client: &reqwest::blocking::Client,
+        creds: &Credentials,
+        list_id: &str,
+    ) -> MailgunResult<BulkJob> {
+        let request_builder = client.get(bulk_url(creds, list_id));
+        get_bulk_job_status_with_request_builder(request_builder, creds)
+    }
+
+    /// Same as `get_bulk_job_status` but with an externally managed request builder.
+    /// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+    #[cfg(feature = "blocking")]
+    pub fn get_bulk_job_status_with_request_builder(
+        request_builder: reqwest::blocking::RequestBuilder,
+        creds: &Credentials,
+    ) -> MailgunResult<BulkJob> {
+        let res = request_builder
+            .basic_auth("api", Some(creds.api_key.clone()))
+            .send()?;
+        let res = check_status(res)?;
+
+        let parsed: BulkJob = res.json()?;
+        Ok(parsed)
+    }
+
+    /// Download a finished job's results, the file behind `download_url.csv`. Mailgun serves
+    /// it compressed, so the bytes are returned as-is. Jobs that aren't `Uploaded` yet are an
+    /// `InvalidArgument` error
+    #[cfg(feature = "blocking")]
+    pub fn get_bulk_results(creds: &Credentials, list_id: &str) -> MailgunResult<Vec<u8>> {
+        let client = blocking_client(creds)?;
+        get_bulk_results_with_client(&client, creds, list_id)
+    }
+
+    /// Same as `get_bulk_results` but with an externally managed client
+    #[cfg(feature = "blocking")]
+    pub fn get_bulk_results_with_client(
+        client: &reqwest::blocking::Client,
+        creds: &Credentials,
+        list_id: &str,
+    ) -> MailgunResult<Vec<u8>> {
+        let job = get_bulk_job_status_with_client(client, creds, list_id)?;
+        let urls = match (job.status, job.download_url) {
+            (BulkJobStatus::Uploaded, Some(urls)) => urls,
+            (status, _) => {
+                return Err(crate::MailgunError::InvalidArgument(format!(
+                    "bulk job {} has no results yet, it is {:?}",
+                    list_id, status
+                )))
+            }
+        };
+        // presigned, sending our basic auth along would be rejected
+        let res = check_status(client.get(urls.csv).send()?)?;
+        Ok(res.bytes()?.to_vec())
+    }
+
+    #[cfg(all(test, feature = "blocking"))]
+    mod tests {
+        use super::*;
+        use serde_json::json;
+
+        const KEY: &str = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+
+        #[test]
+        fn create_job() {
+            let base = format!("{}/bulk-create/v3", mockito::server_url());
+            let creds = Credentials::with_base(base, KEY, "bulk.example.com");
+            let m = mockito::mock("POST", "/bulk-create/v4/address/validate/bulk/newsletter")
+                .match_body(mockito::Matcher::AllOf(vec![
+                    mockito::Matcher::Regex(
+                        r#"name="file"; filename="addresses.csv"\r\nContent-Type: text/csv"#
+                            .to_string(),
+                    ),
+                    mockito::Matcher::Regex("email\na@example.com\nb@example.com\n".to_string()),
+                ]))
+                .with_status(200)
+                .with_body(
+                    json!({"id": "newsletter", "message": "The validation job was submitted."})
+                        .to_string(),
+                )
+                .create();
+            let res = create_bulk_job(&creds, "newsletter", &["a@example.com", " b@example.com"]);
+            assert!(res.is_ok(), "{:?}", &res);
+            m.assert();
+            assert_eq!(res.unwrap().id, "newsletter");
+        }
+
+        #[test]
+        fn poll_and_download() {
+            let base = format!("{}/bulk-poll/v3", mockito::server_url());
+            let creds = Credentials::with_base(base, KEY, "bulk.example.com");
+            let path = "/bulk-poll/v4/address/validate/bulk/newsletter";
+            let csv_url = format!("{}/bulk-results/newsletter.csv.zip", mockito::server_url());
+
+            let processing = mockito::mock("GET", path)
+                .with_status(200)
+                .with_body(
+                    json!({
+                        "id": "newsletter",
+                        "status": "processing",
+                        "quantity": 2,
+                        "records_processed": 1,
+                        "created_at": 1590080191,
+                        "download_url": null
+                    })
+                    .to_string(),
+                )
+                .expect(2)
+                .create();
+            let job = get_bulk_job_status(&creds, "newsletter").unwrap();
+            assert_eq!(job.status, BulkJobStatus::Processing);
+            assert_eq!(job.records_processed, 1);
+            match get_bulk_results(&creds, "newsletter") {
+                Err(crate::MailgunError::InvalidArgument(_)) => {}
+                other => panic!("expected an unfinished job error, got {:?}", other),
+            }
+            processing.assert();
+
+            let uploaded = mockito::mock("GET", path)
+                .with_status(200)
+                .with_body(
+                    json!({
+                        "id": "newsletter",
+                        "status": "uploaded",
+                        "quantity": 2,
+                        "records_processed": 2,
+                        "created_at": 1590080191,
+                        "download_url": {"csv": csv_url, "json": "unused"}
+                    })
+                    .to_string(),
+                )
+                .create();
+            let download = mockito::mock("GET", "/bulk-results/newsletter.csv.zip")
+                .match_header("authorization", mockito::Matcher::Missing)
+                .with_status(200)
+                .with_body("PK results")
+                .create();
+            let res = get_bulk_results(&creds, "newsletter");
+            assert!(res.is_ok(), "{:?}", &res);
+            uploaded.assert();
+            download.assert();
+            assert_eq!(res.unwrap(), b"PK results".to_vec());
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+pub mod async_impl {
+    use super::*;
+    use crate::{async_client, check_status_async};
+
+    /// Validate an email using mailgun's validation service
+    /// [API docs](https://documentation.mailgun.com/en/latest/api-email-validation.html#email-validation)
+    pub async fn validate_email(
+        creds: &Credentials,
+        address: &str,
+    ) -> MailgunResult<ValidationResponse> {
+        let client = async_client(creds)?;
+        validate_email_with_client(&client, creds, address).await
+    }
+
+    /// Same as `validate_email` but with an externally managed client
+    pub async fn validate_email_with_client(
+        client: &reqwest::Client,
+        creds: &Credentials,
+        address: &str,
+    ) -> MailgunResult<ValidationResponse> {
+        let url = format!("{}/{}", creds.api_base, VALIDATION_ENDPOINT);
+        let request_builder = client.get(url);
+        validate_email_with_request_builder(request_builder, creds, address).await
+    }
+
+    /// Same as `validate_email` but with an externally managed request builder.
+    /// Use this in case you want to send the request to a custom API endpoint, e.g. for testing.
+    pub async fn validate_email_with_request_builder(
+        request_builder: reqwest::RequestBuilder,
+        creds: &Credentials,
+        address: &str,
+    ) -> MailgunResult<ValidationResponse> {
+        let mut params = HashMap::new();
+        params.insert("address".to_string(), address);
+
+        let res = request_builder
+            .basic_auth("api", Some(creds.api_key.clone()))
+            .form(&params)
+            .send()
+            .await?;
+        let res = check_status_async(res).await?;
+
+        let parsed: ValidationResponse = res.json().await?;
+        Ok(parsed)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use serde_json::json;
+
+        #[tokio::test]
+        async fn test_validate_with_request_builder() {
+            let key = "0123456789abcdef0123456789abcdef-01234567-89abcdef";
+            let creds = Credentials::new(key, "async-validation.example.com");
+            let response = json!({
+                "address": "foo@example.com",
+                "did_you_mean": "foo@example.org",
+                "is_disposable_address": false,
+                "is_role_address": true,
+                "is_valid": false,
+                "parts": null,
+                "reason": "mailbox_does_not_exist"
+            });
+
+            let uri = "/async/address/private/validate";
+            let _m = mockito::mock("GET", uri)
+                .match_body(mockito::Matcher::UrlEncoded(
+                    "address".to_string(),
+                    "foo@example.com".to_string(),
+                ))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(response.to_string())
+                .create();
+
+            let url = format!("{}{}", mockito::server_url(), uri);
+            let client = reqwest::Client::new();
+            let request_builder = client.get(&url);
+            let res =
+                validate_email_with_request_builder(request_builder, &creds, "foo@example.com")
+                    .await;
+            assert!(res.is_ok(), "{:?}", &res);
+            let parsed = res.unwrap();
+            assert!(!parsed.is_valid);
+            assert!(parsed.is_role_address);
+            assert_eq!(parsed.did_you_mean, Some("foo@example.org".to_string()));
+            assert_eq!(parsed.reason, Some("mailbox_does_not_exist".to_string()));
+        }
+    }
+}